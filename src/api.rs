@@ -0,0 +1,113 @@
+use std::collections::HashMap;
+
+use serde::Deserialize;
+
+// The most titles the Action API accepts in one query for clients without the apihighlimits right
+pub const MAX_TITLES_PER_QUERY: usize = 50;
+
+// Subset of the 'action=query&formatversion=2' response to 'prop=links|categories' and 'list=categorymembers'
+#[derive(Debug, Deserialize)]
+pub struct QueryResponse {
+    #[serde(rename = "continue")]
+    pub continuation: Option<HashMap<String, String>>,
+    pub query: Option<QueryResult>,
+    pub error: Option<ApiErrorInfo>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct QueryResult {
+    #[serde(default)]
+    pub normalized: Vec<TitleMapping>,
+    #[serde(default)]
+    pub redirects: Vec<TitleMapping>,
+    #[serde(default)]
+    pub pages: Vec<QueryPage>,
+    // Only present for 'list=categorymembers'
+    #[serde(default)]
+    pub categorymembers: Vec<PageLink>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TitleMapping {
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct QueryPage {
+    pub title: String,
+    #[serde(default)]
+    pub missing: bool,
+    #[serde(default)]
+    pub invalid: bool,
+    #[serde(default)]
+    pub links: Vec<PageLink>,
+    #[serde(default)]
+    pub categories: Vec<PageLink>,
+    // Only present when 'info' is one of the requested props
+    pub lastrevid: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PageLink {
+    pub ns: i64,
+    pub title: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ApiErrorInfo {
+    pub code: String,
+    pub info: String,
+}
+
+// Links merged across every continuation of a single query
+#[derive(Debug, Default)]
+pub struct MergedQuery {
+    // Title as requested -> title after normalization and redirects
+    pub resolved: HashMap<String, String>,
+    // Page title -> titles linked from the page. Missing and invalid pages have no entry
+    pub links: HashMap<String, Vec<String>>,
+    // Page title -> categories the page is in
+    pub categories: HashMap<String, Vec<String>>,
+    // Titles listed by 'list=categorymembers'
+    pub members: Vec<String>,
+    // Page title -> latest revision id
+    pub revisions: HashMap<String, u64>,
+}
+
+impl MergedQuery {
+    pub fn merge(&mut self, result: QueryResult) {
+        for mapping in result.normalized.into_iter().chain(result.redirects) {
+            self.resolved.insert(mapping.from, mapping.to);
+        }
+        for page in result.pages {
+            if page.missing || page.invalid {
+                continue;
+            }
+            if let Some(revision) = page.lastrevid {
+                self.revisions.insert(page.title.clone(), revision);
+            }
+            let categories = self.categories.entry(page.title.clone()).or_default();
+            categories.extend(page.categories.into_iter().map(|category| category.title));
+            let links = self.links.entry(page.title).or_default();
+            links.extend(page.links.into_iter().map(|link| link.title));
+        }
+        self.members.extend(result.categorymembers.into_iter().map(|member| member.title));
+    }
+
+    // Follows normalization then redirects. Returns None if the page does not exist
+    pub fn resolve<'a>(&'a self, title: &'a str) -> Option<&'a str> {
+        let mut current = title;
+        // Normalization and a single redirect are the only hops the API reports
+        for _ in 0..2 {
+            if self.links.contains_key(current) {
+                break;
+            }
+            match self.resolved.get(current) {
+                Some(next) => current = next,
+                None => break,
+            }
+        }
+        self.links.contains_key(current).then_some(current)
+    }
+}
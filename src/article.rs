@@ -4,45 +4,91 @@ use std::fmt;
 use log::{debug, error};
 use once_cell::sync::Lazy;
 use scraper::{selectable::Selectable, ElementRef, Html, Selector};
+use serde::{Deserialize, Serialize};
+
+use crate::section::Section;
+use crate::site::DEFAULT_ARTICLE_PATH;
+use crate::text::extract_text;
+use crate::title::{Title, MAIN_NAMESPACE};
 
 const ARTICLE_BODY_CSS: &str = "#mw-content-text";
 const HEADING_CSS: &str = "#firstHeading span";
+const WHAT_LINKS_HERE_CSS: &str = "#mw-whatlinkshere-list";
+const INFOBOX_CLASS: &str = "infobox";
+const INFOBOX_SUBBOX_CLASS: &str = "infobox-subbox";
 
 static ARTICLE_BODY_SELECTOR: Lazy<Selector> = Lazy::new(|| Selector::parse(ARTICLE_BODY_CSS).unwrap());
 static HEADING_SELECTOR: Lazy<Selector> = Lazy::new(|| Selector::parse(HEADING_CSS).unwrap());
-static LINK_SELECTOR: Lazy<Selector> = Lazy::new(|| Selector::parse("a[href^='/wiki/'").unwrap());
+// Hrefs are matched against the article path of the site the page came from, which is not always '/wiki/'
+static LINK_SELECTOR: Lazy<Selector> = Lazy::new(|| Selector::parse("a[href]").unwrap());
+static WHAT_LINKS_HERE_SELECTOR: Lazy<Selector> = Lazy::new(|| Selector::parse(WHAT_LINKS_HERE_CSS).unwrap());
+static INFOBOX_SELECTOR: Lazy<Selector> = Lazy::new(|| Selector::parse("table.infobox").unwrap());
+static INFOBOX_TITLE_SELECTOR: Lazy<Selector> = Lazy::new(|| Selector::parse("caption, .infobox-above, .infobox-title").unwrap());
+static INFOBOX_CAPTION_SELECTOR: Lazy<Selector> = Lazy::new(|| Selector::parse(".infobox-caption").unwrap());
+static IMAGE_SELECTOR: Lazy<Selector> = Lazy::new(|| Selector::parse("img").unwrap());
+static CATEGORY_SELECTOR: Lazy<Selector> = Lazy::new(|| Selector::parse("#mw-normal-catlinks li a").unwrap());
+static HIDDEN_CATEGORY_SELECTOR: Lazy<Selector> = Lazy::new(|| Selector::parse("#mw-hidden-catlinks li a").unwrap());
+static SUBCATEGORY_SELECTOR: Lazy<Selector> = Lazy::new(|| Selector::parse("#mw-subcategories li a[href]").unwrap());
+static CATEGORY_PAGE_SELECTOR: Lazy<Selector> = Lazy::new(|| Selector::parse("#mw-pages li a[href]").unwrap());
+// The 'next page' links above and below each listing. The link text depends on the language of the site
+static NEXT_SUBCATEGORIES_SELECTOR: Lazy<Selector> = Lazy::new(|| Selector::parse("#mw-subcategories > a[href*='subcatfrom=']").unwrap());
+static NEXT_WHAT_LINKS_HERE_SELECTOR: Lazy<Selector> = Lazy::new(|| Selector::parse("a.mw-nextlink").unwrap());
+static NEXT_CATEGORY_PAGES_SELECTOR: Lazy<Selector> = Lazy::new(|| Selector::parse("#mw-pages > a[href*='pagefrom=']").unwrap());
 
 pub struct Article {
-    endpoint: String,
+    endpoint: Title,
     html: Html,
+    // Prefix of the hrefs that link to other articles. e.g. '/wiki/'
+    article_path: String,
 }
 
 impl Article {
-    pub fn new(endpoint: String, html: Html) -> Self {
-        Article { endpoint, html }
+    pub fn new(endpoint: Title, html: Html) -> Self {
+        Article {
+            endpoint,
+            html,
+            article_path: DEFAULT_ARTICLE_PATH.to_string(),
+        }
     }
 
-    pub fn get_endpoint(&self) -> &str {
+    // For pages from sites that serve articles somewhere other than '/wiki/'
+    pub fn with_article_path(mut self, article_path: &str) -> Self {
+        self.article_path = article_path.to_string();
+        self
+    }
+
+    pub fn get_endpoint(&self) -> &Title {
         &self.endpoint
     }
 
     pub fn get_lead_string(&self) -> Result<String, ArticleError> {
-        let inner_nodes = self.get_article_body()?.children();
-        let mut lead_paragraphs = Vec::new();
-        for node in inner_nodes {
-            if let Some(element) = node.value().as_element() {
-                match element.name() {
-                    "p" => lead_paragraphs.push(ElementRef::wrap(node).ok_or(ArticleError::ElementError)?),
-                    "h2" => break,
-                    _ => continue,
-                }
-            }
-        }
-        let mut lead_string = String::new();
-        for paragraph in lead_paragraphs {
-            lead_string.push_str(&paragraph.html());
-        }
-        Ok(lead_string)
+        let lead = self.get_sections()?;
+        Ok(lead.get_paragraphs().iter().map(|paragraph| paragraph.html()).collect())
+    }
+
+    // Plain text of the paragraphs before the first heading, without citation markers, pronunciations or navigation
+    pub fn get_lead_text(&self) -> Result<String, ArticleError> {
+        Ok(self.get_sections()?.get_content_text())
+    }
+
+    // Plain text of the named section and its subsections, or None if there is no such section
+    pub fn get_section_text(&self, name: &str) -> Result<Option<String>, ArticleError> {
+        Ok(self.get_sections()?.find(name).map(Section::get_text))
+    }
+
+    // Plain text of the whole article with section headings on their own lines
+    pub fn get_text(&self) -> Result<String, ArticleError> {
+        Ok(self.get_sections()?.get_text())
+    }
+
+    // Returns the lead, with the article's sections nested below it by heading level
+    pub fn get_sections(&self) -> Result<Section<'_>, ArticleError> {
+        Ok(Section::parse(self.get_article_body()?, &self.article_path))
+    }
+
+    // Returns the first section whose heading or anchor matches the name
+    pub fn get_section(&self, name: &str) -> Result<Option<Section<'_>>, ArticleError> {
+        Ok(self.get_sections()?.find(name).cloned())
     }
 
     pub fn get_article_body(&self) -> Result<ElementRef<'_>, ArticleError> {
@@ -65,22 +111,275 @@ impl Article {
         Ok(heading_span.inner_html())
     }
 
-    pub fn get_article_link_refs(&self) -> Result<Vec<&'_ str>, ArticleError> {
-        let article_body = self.get_article_body()?;
-        let links = article_body.select(&LINK_SELECTOR);
-        let mut endpoints = Vec::new();
+    // Returns every infobox in the article in document order. Infoboxes embedded in another one are part of it
+    pub fn get_infoboxes(&self) -> Result<Vec<Infobox>, ArticleError> {
+        let body = self.get_article_body()?;
+        let infoboxes = body
+            .select(&INFOBOX_SELECTOR)
+            .filter(|table| {
+                !table
+                    .ancestors()
+                    .filter_map(ElementRef::wrap)
+                    .any(|ancestor| has_class(ancestor, INFOBOX_CLASS))
+            })
+            .map(|table| Infobox::from_table(table, &self.article_path))
+            .collect();
+        Ok(infoboxes)
+    }
+
+    // Categories listed at the bottom of the page, visible ones first. Empty if the page is in no category
+    pub fn get_categories(&self) -> Vec<CategoryLink> {
+        let mut categories = Vec::new();
+        for (selector, hidden) in [(&CATEGORY_SELECTOR, false), (&HIDDEN_CATEGORY_SELECTOR, true)] {
+            for link in self.html.select(selector) {
+                match link
+                    .value()
+                    .attr("href")
+                    .and_then(|href| category_link_title(href, &self.article_path))
+                {
+                    Some(title) => categories.push(CategoryLink { title, hidden }),
+                    None => debug!("Skipping category link '{}'", link.html()),
+                }
+            }
+        }
+        categories
+    }
+
+    // Only meaningful for category pages. Only the subcategories listed on this page of the category are returned
+    pub fn get_subcategories(&self) -> Vec<Title> {
+        let mut titles = Vec::new();
+        for link in self.html.select(&SUBCATEGORY_SELECTOR) {
+            if let Some(endpoint) = link
+                .value()
+                .attr("href")
+                .and_then(|href| href.strip_prefix(self.article_path.as_str()))
+            {
+                push_link(&mut titles, endpoint);
+            }
+        }
+        titles
+    }
+
+    // Only meaningful for category pages. Only the member pages listed on this page of the category are returned
+    pub fn get_category_pages(&self) -> Vec<Title> {
+        let mut titles = Vec::new();
+        for link in self.html.select(&CATEGORY_PAGE_SELECTOR) {
+            if let Some(endpoint) = link
+                .value()
+                .attr("href")
+                .and_then(|href| href.strip_prefix(self.article_path.as_str()))
+            {
+                push_link(&mut titles, endpoint);
+            }
+        }
+        titles
+    }
+
+    // Href of the listing page with the subcategories after the ones on this page. e.g.
+    // '/w/index.php?title=Category:Philosophy_of_mind&subcatfrom=Qualia#mw-subcategories'. None on the last page
+    pub fn get_next_subcategories_ref(&self) -> Option<String> {
+        self.html
+            .select(&NEXT_SUBCATEGORIES_SELECTOR)
+            .next()
+            .and_then(|link| link.value().attr("href"))
+            .map(str::to_string)
+    }
+
+    // Href of the listing page with the member pages after the ones on this page. None on the last page
+    pub fn get_next_category_pages_ref(&self) -> Option<String> {
+        self.html
+            .select(&NEXT_CATEGORY_PAGES_SELECTOR)
+            .next()
+            .and_then(|link| link.value().attr("href"))
+            .map(str::to_string)
+    }
+
+    pub fn get_article_link_refs(&self) -> Result<Vec<Title>, ArticleError> {
+        Ok(find_article_links(self.get_article_body()?, &self.article_path))
+    }
+
+    // Only meaningful for Special:WhatLinksHere pages. Redirect pages are linked through '/w/index.php' so only the
+    // articles that link to the target (directly or through a redirect) are returned. Articles with a colon in their
+    // title are kept, only pages in other namespaces are skipped
+    pub fn get_what_links_here_refs(&self) -> Result<Vec<Title>, ArticleError> {
+        let list = self
+            .html
+            .select(&WHAT_LINKS_HERE_SELECTOR)
+            .next()
+            .ok_or(ArticleError::MissingLinkList)?;
+        let links = list.select(&LINK_SELECTOR);
+        let mut titles = Vec::new();
         for link in links {
-            if let Some(href) = link.value().attr("href") {
-                if let Some(wiki_link) = href.strip_prefix("/wiki/") {
-                    if !wiki_link.contains(':') {
-                        let page_wiki_link = wiki_link.split('#').next().expect("Will always have one element in split");
-                        endpoints.push(page_wiki_link);
+            if let Some(wiki_link) = link
+                .value()
+                .attr("href")
+                .and_then(|href| href.strip_prefix(self.article_path.as_str()))
+            {
+                push_link(&mut titles, wiki_link);
+            }
+        }
+        titles.retain(|title| title.get_namespace() == MAIN_NAMESPACE);
+
+        Ok(titles)
+    }
+
+    // Only meaningful for Special:WhatLinksHere pages. Href of the page with the links after the ones on this page. None
+    // on the last page
+    pub fn get_next_what_links_here_ref(&self) -> Option<String> {
+        self.html
+            .select(&NEXT_WHAT_LINKS_HERE_SELECTOR)
+            .next()
+            .and_then(|link| link.value().attr("href"))
+            .map(str::to_string)
+    }
+}
+
+// Summary table of an article, usually at the top right
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Infobox {
+    pub title: Option<String>,
+    pub image: Option<InfoboxImage>,
+    // In the order they appear in the table
+    pub fields: Vec<InfoboxField>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InfoboxImage {
+    // File page the image links to. e.g. 'File:Descartes.jpg'
+    pub file: Option<Title>,
+    // Url of the scaled image as written in the page, which is usually protocol relative
+    pub source: String,
+    pub caption: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InfoboxField {
+    pub label: String,
+    // Plain text with one line per list item or line break
+    pub value: String,
+    // Articles linked from the value
+    pub links: Vec<Title>,
+}
+
+impl Infobox {
+    fn from_table(table: ElementRef<'_>, article_path: &str) -> Self {
+        let title = table
+            .select(&INFOBOX_TITLE_SELECTOR)
+            .next()
+            .map(|element| extract_text([element]).replace('\n', " "));
+        let mut infobox = Infobox {
+            title: title.filter(|title| !title.is_empty()),
+            image: None,
+            fields: Vec::new(),
+        };
+
+        for row in Self::rows(table) {
+            let cells: Vec<ElementRef<'_>> = row
+                .child_elements()
+                .filter(|cell| matches!(cell.value().name(), "th" | "td"))
+                .collect();
+            match cells[..] {
+                [label, value] if label.value().name() == "th" || has_class(label, "infobox-label") => {
+                    let label_text = extract_text([label]).replace('\n', " ");
+                    if !label_text.is_empty() {
+                        infobox.fields.push(InfoboxField {
+                            label: label_text,
+                            value: extract_text([value]),
+                            links: find_article_links(value, article_path),
+                        });
                     }
                 }
+                [cell] if infobox.image.is_none() => infobox.image = InfoboxImage::from_cell(cell, article_path),
+                _ => (),
             }
         }
+        infobox
+    }
+
+    // Rows of the table followed by the rows of any subbox nested in them, so fields keep their order
+    fn rows(table: ElementRef<'_>) -> Vec<ElementRef<'_>> {
+        let mut rows = Vec::new();
+        for child in table.child_elements() {
+            let group_rows: Vec<ElementRef<'_>> = match child.value().name() {
+                "tr" => vec![child],
+                "thead" | "tbody" | "tfoot" => child.child_elements().filter(|row| row.value().name() == "tr").collect(),
+                _ => continue,
+            };
+            for row in group_rows {
+                rows.push(row);
+                for cell in row.child_elements() {
+                    for subbox in cell.child_elements().filter(|element| has_class(*element, INFOBOX_SUBBOX_CLASS)) {
+                        rows.extend(Self::rows(subbox));
+                    }
+                }
+            }
+        }
+        rows
+    }
+}
+
+impl InfoboxImage {
+    // Returns None if the cell has no image
+    fn from_cell(cell: ElementRef<'_>, article_path: &str) -> Option<Self> {
+        let image = cell.select(&IMAGE_SELECTOR).next()?;
+        let file = cell
+            .select(&LINK_SELECTOR)
+            .filter_map(|link| link.value().attr("href")?.strip_prefix(article_path))
+            .find_map(|endpoint| Title::from_endpoint(endpoint).ok());
+        let caption = cell
+            .select(&INFOBOX_CAPTION_SELECTOR)
+            .next()
+            .map(|element| extract_text([element]).replace('\n', " "));
+        Some(InfoboxImage {
+            file,
+            source: image.value().attr("src").unwrap_or_default().to_string(),
+            caption: caption.filter(|caption| !caption.is_empty()),
+        })
+    }
+}
+
+fn has_class(element: ElementRef<'_>, class: &str) -> bool {
+    element.value().classes().any(|element_class| element_class == class)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CategoryLink {
+    pub title: Title,
+    // Maintenance categories like 'Category:Articles with short description' that readers do not see
+    pub hidden: bool,
+}
+
+// Categories that do not have a page yet are linked through '/w/index.php?title=Category:...&action=edit&redlink=1'
+fn category_link_title(href: &str, article_path: &str) -> Option<Title> {
+    if let Some(endpoint) = href.strip_prefix(article_path) {
+        return Title::from_endpoint(endpoint).ok();
+    }
+    let (_, query) = href.split_once('?')?;
+    let endpoint = query.split('&').find_map(|param| param.strip_prefix("title="))?;
+    Title::from_endpoint(endpoint).ok()
+}
+
+// Links to articles in the main namespace from anywhere inside the element
+pub(crate) fn find_article_links(element: ElementRef<'_>, article_path: &str) -> Vec<Title> {
+    let mut titles = Vec::new();
+    for link in element.select(&LINK_SELECTOR) {
+        if let Some(href) = link.value().attr("href") {
+            if let Some(wiki_link) = href.strip_prefix(article_path) {
+                let page_wiki_link = wiki_link.split('#').next().expect("Will always have one element in split");
+                push_link(&mut titles, page_wiki_link);
+            }
+        }
+    }
+    // Filtered on the parsed title so articles with a colon in their name are kept
+    titles.retain(|title| title.get_namespace() == MAIN_NAMESPACE);
+    titles
+}
 
-        Ok(endpoints)
+// MediaWiki never produces hrefs that fail to decode, so any that do are skipped rather than failing the article
+fn push_link(titles: &mut Vec<Title>, endpoint: &str) {
+    match Title::from_endpoint(endpoint) {
+        Ok(title) => titles.push(title),
+        Err(e) => debug!("Skipping link to '{}'; Reason: {}", endpoint, e),
     }
 }
 
@@ -89,6 +388,7 @@ pub enum ArticleError {
     MissingBodyParent,
     MissingBody,
     MissingHeading,
+    MissingLinkList,
     ElementError,
 }
 
@@ -98,6 +398,7 @@ impl fmt::Display for ArticleError {
             Self::MissingBodyParent => write!(f, "Cannot find element with css '{}'", ARTICLE_BODY_CSS),
             Self::MissingBody => write!(f, "Cannot find child of element with css '{}'", ARTICLE_BODY_CSS),
             Self::MissingHeading => write!(f, "Cannot find element with css '{}'", HEADING_CSS),
+            Self::MissingLinkList => write!(f, "Cannot find element with css '{}'", WHAT_LINKS_HERE_CSS),
             Self::ElementError => write!(f, "Failed to convert node to element"),
         }
     }
@@ -0,0 +1,232 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use log::{debug, trace};
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+
+use crate::site::Site;
+
+const REVISION_ID_MARKER: &str = "\"wgRevisionId\":";
+
+// Numbers the temporary files of this process so concurrent writes never share one
+static TEMP_FILE_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CacheMode {
+    // Serve fresh entries, revalidate stale ones and store everything retrieved
+    #[default]
+    ReadWrite,
+    // Serve every entry regardless of age and never touch the network. Misses are errors
+    Offline,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContentKind {
+    Html,
+    Links,
+}
+
+impl ContentKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Html => "html",
+            Self::Links => "links",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum CachedContent {
+    Html(String),
+    // Written before categories were retrieved. Never served so the page is retrieved again
+    Links(Vec<String>),
+    PageLinks {
+        links: Vec<String>,
+        categories: Vec<String>,
+        members: Vec<String>,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheEntry {
+    key: String,
+    // Endpoint the request resolved to after redirects
+    pub endpoint: String,
+    pub revision: Option<u64>,
+    fetched_at: u64,
+    pub content: CachedContent,
+}
+
+pub enum CacheLookup {
+    Fresh(CacheEntry),
+    // Older than the TTL. Can be served if its revision is still the latest
+    Stale(CacheEntry),
+    Miss,
+}
+
+// Stores retrieved pages and link lists on disk as one json file per entry, keyed by site and requested endpoint
+#[derive(Debug, Clone)]
+pub struct ArticleCache {
+    directory: PathBuf,
+    ttl: Duration,
+    mode: CacheMode,
+}
+
+impl ArticleCache {
+    pub fn new(directory: impl Into<PathBuf>, ttl: Duration) -> Self {
+        ArticleCache {
+            directory: directory.into(),
+            ttl,
+            mode: CacheMode::default(),
+        }
+    }
+
+    pub fn set_mode(&mut self, mode: CacheMode) {
+        self.mode = mode;
+    }
+
+    pub fn get_mode(&self) -> CacheMode {
+        self.mode
+    }
+
+    pub fn get_directory(&self) -> &Path {
+        &self.directory
+    }
+
+    pub async fn lookup(&self, site: &Site, content_kind: ContentKind, endpoint: &str) -> CacheLookup {
+        let key = Self::key(site, content_kind, endpoint);
+        let path = self.path_for(site, &key);
+        let entry = match Self::read_entry(&path).await {
+            Ok(entry) if entry.key == key => entry,
+            Ok(_) => {
+                debug!("Cache key collision at {}", path.display());
+                return CacheLookup::Miss;
+            }
+            Err(e) => {
+                if e.kind() != io::ErrorKind::NotFound {
+                    debug!("Failed to read cache entry {}; Reason: {}", path.display(), e);
+                }
+                return CacheLookup::Miss;
+            }
+        };
+
+        let age = Duration::from_secs(now_secs().saturating_sub(entry.fetched_at));
+        if self.mode == CacheMode::Offline || age <= self.ttl {
+            trace!("Cache hit for {}", key);
+            CacheLookup::Fresh(entry)
+        } else {
+            trace!("Stale cache entry for {}", key);
+            CacheLookup::Stale(entry)
+        }
+    }
+
+    pub async fn store(
+        &self,
+        site: &Site,
+        content_kind: ContentKind,
+        endpoint: &str,
+        final_endpoint: &str,
+        revision: Option<u64>,
+        content: CachedContent,
+    ) -> Result<(), CacheError> {
+        let key = Self::key(site, content_kind, endpoint);
+        let entry = CacheEntry {
+            key: key.clone(),
+            endpoint: final_endpoint.to_string(),
+            revision,
+            fetched_at: now_secs(),
+            content,
+        };
+        self.write_entry(site, &key, &entry).await
+    }
+
+    // Resets the age of an entry whose revision was confirmed to still be the latest
+    pub async fn touch(&self, site: &Site, content_kind: ContentKind, endpoint: &str, mut entry: CacheEntry) -> Result<(), CacheError> {
+        let key = Self::key(site, content_kind, endpoint);
+        entry.fetched_at = now_secs();
+        self.write_entry(site, &key, &entry).await
+    }
+
+    async fn write_entry(&self, site: &Site, key: &str, entry: &CacheEntry) -> Result<(), CacheError> {
+        let path = self.path_for(site, key);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        // Write to a temporary file first so concurrent readers never see a partial entry. Each write has its own so
+        // two writers of the same entry, in this process or another, can't rename each other's partial file
+        let temp_number = TEMP_FILE_COUNTER.fetch_add(1, Ordering::Relaxed);
+        let temp_path = path.with_extension(format!("json.{}-{}.tmp", process::id(), temp_number));
+        fs::write(&temp_path, serde_json::to_vec(entry)?).await?;
+        fs::rename(&temp_path, &path).await?;
+        trace!("Cached {} at {}", key, path.display());
+        Ok(())
+    }
+
+    async fn read_entry(path: &Path) -> io::Result<CacheEntry> {
+        let bytes = fs::read(path).await?;
+        serde_json::from_slice(&bytes).map_err(io::Error::from)
+    }
+
+    fn key(site: &Site, content_kind: ContentKind, endpoint: &str) -> String {
+        format!("{}|{}|{}", content_kind.as_str(), site.base_url(), endpoint)
+    }
+
+    fn path_for(&self, site: &Site, key: &str) -> PathBuf {
+        let host_dir = site.get_host().replace(':', "_");
+        self.directory.join(host_dir).join(format!("{:016x}.json", fnv1a_hash(key)))
+    }
+}
+
+// Finds the revision id MediaWiki embeds in the page config of every rendered article
+pub fn extract_revision_id(html: &str) -> Option<u64> {
+    let start = html.find(REVISION_ID_MARKER)? + REVISION_ID_MARKER.len();
+    let digits: String = html[start..].chars().take_while(|c| c.is_ascii_digit()).collect();
+    digits.parse().ok().filter(|revision| *revision != 0)
+}
+
+// Stable across runs and compiler versions, unlike DefaultHasher
+pub(crate) fn fnv1a_hash(s: &str) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+    const PRIME: u64 = 0x100000001b3;
+
+    s.bytes().fold(OFFSET_BASIS, |hash, byte| (hash ^ byte as u64).wrapping_mul(PRIME))
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())
+}
+
+#[derive(Debug)]
+pub enum CacheError {
+    IoError(io::Error),
+    SerializeError(serde_json::Error),
+}
+
+impl fmt::Display for CacheError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::IoError(e) => write!(f, "Cache io failed: {}", e),
+            Self::SerializeError(e) => write!(f, "Failed to serialize cache entry: {}", e),
+        }
+    }
+}
+
+impl From<io::Error> for CacheError {
+    fn from(e: io::Error) -> CacheError {
+        CacheError::IoError(e)
+    }
+}
+
+impl From<serde_json::Error> for CacheError {
+    fn from(e: serde_json::Error) -> CacheError {
+        CacheError::SerializeError(e)
+    }
+}
+
+impl Error for CacheError {}
@@ -0,0 +1,342 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::error::Error;
+use std::fmt;
+use std::io::Write;
+use std::sync::Arc;
+
+use futures::future::join_all;
+use log::{debug, error, info};
+use tokio::task::JoinError;
+
+use crate::client::{AsyncClient, ClientError};
+use crate::report::{CategoryLayerReport, CategoryReport, CycleReport, FailureReport};
+use crate::title::{Title, CATEGORY_NAMESPACE};
+
+// Categories expanded at one depth along with the member pages first found at it
+struct CategoryLayer {
+    categories: BTreeSet<Title>,
+    pages: BTreeSet<Title>,
+}
+
+// Subcategories and member pages from every page of a category's listing
+struct CategoryListing {
+    subcategories: Vec<Title>,
+    pages: Vec<Title>,
+}
+
+// Enumerates the pages under a category breadth first. Layer n holds the categories n subcategory links below the root
+// and the pages in them that are not in a category closer to the root.
+//
+// Each category is only expanded once, so a cycle in the category graph ends the descent where it closes. A
+// subcategory link back to a category that already leads to the linking category is reported as a cycle.
+pub struct CategoryCrawler {
+    client: Arc<AsyncClient>,
+    root: Title,
+    layers: Vec<CategoryLayer>,
+    // Categories of the next layer
+    frontier: BTreeSet<Title>,
+    // Every category in a layer or the frontier
+    visited: HashSet<Title>,
+    // Every page in a layer
+    found_pages: HashSet<Title>,
+    // Category -> subcategories listed on it
+    edges: HashMap<Title, BTreeSet<Title>>,
+    // Each starts and ends with the same category
+    cycles: Vec<Vec<Title>>,
+    // Category -> reason its listing could not be retrieved
+    failures: BTreeMap<Title, String>,
+}
+
+impl CategoryCrawler {
+    pub fn new(root: Title) -> Result<Self, CategoryCrawlError> {
+        if root.get_namespace() != CATEGORY_NAMESPACE {
+            return Err(CategoryCrawlError::NotACategory(root.to_string()));
+        }
+        Ok(CategoryCrawler {
+            client: Arc::new(AsyncClient::new()),
+            frontier: BTreeSet::from([root.clone()]),
+            visited: HashSet::from([root.clone()]),
+            root,
+            layers: Vec::new(),
+            found_pages: HashSet::new(),
+            edges: HashMap::new(),
+            cycles: Vec::new(),
+            failures: BTreeMap::new(),
+        })
+    }
+
+    // Category pages are always scraped, whatever the client's link backend
+    pub fn set_client(&mut self, client: Arc<AsyncClient>) {
+        self.client = client;
+    }
+
+    pub fn get_root(&self) -> &Title {
+        &self.root
+    }
+
+    pub fn get_layer_count(&self) -> usize {
+        self.layers.len()
+    }
+
+    // Whether every category under the root has been expanded
+    pub fn is_complete(&self) -> bool {
+        self.frontier.is_empty()
+    }
+
+    // Expands every category found by the last call. The first call expands the root
+    pub async fn compute_next_async(&mut self) -> Result<(), CategoryCrawlError> {
+        let depth = self.layers.len();
+        let categories = std::mem::take(&mut self.frontier);
+        info!("Expanding {} categories at depth {}", categories.len(), depth);
+
+        let mut handles = Vec::with_capacity(categories.len());
+        for category in categories.iter().cloned() {
+            let client = self.client.clone();
+            handles.push(tokio::spawn(async move {
+                let result = Self::get_listing(&client, &category).await;
+                (category, result)
+            }));
+        }
+
+        // Handled in title order so the same cycle is always reported from the same category
+        let mut pages = BTreeSet::new();
+        for result in join_all(handles).await {
+            let (category, result) = result?;
+            let listing = match result {
+                Ok(listing) => listing,
+                Err(e) => {
+                    error!("Failed to retrieve category '{}'; Reason {}", category, e);
+                    self.failures.insert(category, e.to_string());
+                    continue;
+                }
+            };
+
+            for page in listing.pages {
+                if self.found_pages.insert(page.clone()) {
+                    pages.insert(page);
+                }
+            }
+            for subcategory in listing.subcategories {
+                if !self.edges.entry(category.clone()).or_default().insert(subcategory.clone()) {
+                    continue;
+                }
+                if self.visited.insert(subcategory.clone()) {
+                    self.frontier.insert(subcategory);
+                } else if let Some(mut cycle) = self.find_route(&subcategory, &category) {
+                    cycle.push(subcategory);
+                    info!(
+                        "Found category cycle: {}",
+                        cycle.iter().map(Title::to_string).collect::<Vec<_>>().join(" -> ")
+                    );
+                    self.cycles.push(cycle);
+                } else {
+                    debug!("Category '{}' was already reached before '{}'", subcategory, category);
+                }
+            }
+        }
+
+        self.layers.push(CategoryLayer { categories, pages });
+        Ok(())
+    }
+
+    // Stops early once every category under the root has been expanded
+    pub async fn compute_layers_async(&mut self, count: usize) -> Result<(), CategoryCrawlError> {
+        for _ in 0..count {
+            if self.is_complete() {
+                info!("No categories left to expand after depth {}", self.layers.len().saturating_sub(1));
+                break;
+            }
+            self.compute_next_async().await?;
+        }
+
+        Ok(())
+    }
+
+    // Every page found in any layer
+    pub fn get_pages(&self) -> Vec<Title> {
+        let pages: BTreeSet<&Title> = self.layers.iter().flat_map(|layer| layer.pages.iter()).collect();
+        pages.into_iter().cloned().collect()
+    }
+
+    // Returns the chains of subcategory links that lead back to where they started
+    pub fn get_cycles(&self) -> &[Vec<Title>] {
+        &self.cycles
+    }
+
+    pub fn get_report(&self) -> CategoryReport {
+        let layers = self
+            .layers
+            .iter()
+            .enumerate()
+            .map(|(depth, layer)| CategoryLayerReport {
+                depth,
+                categories: sorted_strings(&layer.categories),
+                pages: sorted_strings(&layer.pages),
+            })
+            .collect();
+
+        let cycles = self
+            .cycles
+            .iter()
+            .map(|cycle| CycleReport {
+                categories: cycle.iter().map(Title::to_string).collect(),
+            })
+            .collect();
+
+        let failures = self
+            .failures
+            .iter()
+            .map(|(category, reason)| FailureReport {
+                article: category.to_string(),
+                reason: reason.clone(),
+            })
+            .collect();
+
+        CategoryReport {
+            root: self.root.to_string(),
+            layers,
+            cycles,
+            failures,
+        }
+    }
+
+    pub fn write_json<W: Write>(&self, writer: &mut W) -> Result<(), CategoryCrawlError> {
+        serde_json::to_writer_pretty(writer, &self.get_report())?;
+        Ok(())
+    }
+
+    // Each listing is paged on its own, so the 'next page' links of both are followed until their last pages
+    async fn get_listing(client: &AsyncClient, category: &Title) -> Result<CategoryListing, ClientError> {
+        let (mut listing, next_subcategories, next_pages) = {
+            let first_page = client.get_article(&category.to_string()).await?;
+            let listing = CategoryListing {
+                subcategories: first_page.get_subcategories(),
+                pages: first_page.get_category_pages(),
+            };
+            (
+                listing,
+                first_page.get_next_subcategories_ref(),
+                first_page.get_next_category_pages_ref(),
+            )
+        };
+
+        let (subcategories, pages) = client
+            .get_remaining_category_members(category, next_subcategories, next_pages)
+            .await?;
+        listing.subcategories.extend(subcategories);
+        listing.pages.extend(pages);
+        debug!(
+            "Found {} subcategories and {} pages in '{}'",
+            listing.subcategories.len(),
+            listing.pages.len(),
+            category
+        );
+
+        Ok(listing)
+    }
+
+    // Returns the shortest chain of subcategory links between the categories, both included
+    fn find_route(&self, from: &Title, to: &Title) -> Option<Vec<Title>> {
+        let mut parents: HashMap<&Title, &Title> = HashMap::new();
+        let mut queue = VecDeque::from([from]);
+        while let Some(current) = queue.pop_front() {
+            if current == to {
+                let mut route = vec![current.clone()];
+                let mut step = current;
+                while let Some(parent) = parents.get(step) {
+                    route.push((*parent).clone());
+                    step = parent;
+                }
+                route.reverse();
+                return Some(route);
+            }
+            for subcategory in self.edges.get(current).into_iter().flatten() {
+                if subcategory != from && !parents.contains_key(subcategory) {
+                    parents.insert(subcategory, current);
+                    queue.push_back(subcategory);
+                }
+            }
+        }
+        None
+    }
+}
+
+fn sorted_strings(titles: &BTreeSet<Title>) -> Vec<String> {
+    let mut strings: Vec<String> = titles.iter().map(Title::to_string).collect();
+    strings.sort();
+    strings
+}
+
+impl fmt::Debug for CategoryCrawler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CategoryCrawler")
+            .field("root", &self.root)
+            .field("frontier", &self.frontier)
+            .field("edges", &self.edges)
+            .field("cycles", &self.cycles)
+            .field("failures", &self.failures)
+            .finish_non_exhaustive()
+    }
+}
+
+impl fmt::Display for CategoryCrawler {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let report = self.get_report();
+        writeln!(f, "Category Name: {}", report.root)?;
+
+        for layer in &report.layers {
+            writeln!(f, "Depth {}:", layer.depth)?;
+            writeln!(f, "\tCategories ({}):", layer.categories.len())?;
+            for category in &layer.categories {
+                writeln!(f, "\t\t{}", category)?;
+            }
+            writeln!(f, "\tPages ({}):", layer.pages.len())?;
+            for page in &layer.pages {
+                writeln!(f, "\t\t{}", page)?;
+            }
+        }
+
+        writeln!(f, "Cycles ({}):", report.cycles.len())?;
+        for cycle in &report.cycles {
+            writeln!(f, "\t{}", cycle.categories.join(" -> "))?;
+        }
+
+        writeln!(f, "Failures ({}):", report.failures.len())?;
+        for failure in &report.failures {
+            writeln!(f, "\t{}: {}", failure.article, failure.reason)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub enum CategoryCrawlError {
+    NotACategory(String),
+    JoinError(JoinError),
+    SerializeError(serde_json::Error),
+}
+
+impl fmt::Display for CategoryCrawlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NotACategory(title) => write!(f, "'{}' is not in the category namespace", title),
+            Self::JoinError(e) => write!(f, "Category task failed: {}", e),
+            Self::SerializeError(e) => write!(f, "Failed to serialize report: {}", e),
+        }
+    }
+}
+
+impl From<JoinError> for CategoryCrawlError {
+    fn from(e: JoinError) -> CategoryCrawlError {
+        CategoryCrawlError::JoinError(e)
+    }
+}
+
+impl From<serde_json::Error> for CategoryCrawlError {
+    fn from(e: serde_json::Error) -> CategoryCrawlError {
+        CategoryCrawlError::SerializeError(e)
+    }
+}
+
+impl Error for CategoryCrawlError {}
@@ -0,0 +1,88 @@
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::process;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use serde::{Deserialize, Serialize};
+
+use crate::title::Title;
+
+// Numbers the temporary files of this process so concurrent saves never share one
+static TEMP_FILE_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+// Serializable state of a LinkCalculator. Titles are written as endpoints
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub layers: Vec<Vec<Title>>,
+    // Layer being calculated when the checkpoint was written
+    pub frontier: Option<FrontierCheckpoint>,
+    pub redirects: Vec<(Title, Title)>,
+    pub edges: Vec<(Title, Vec<Title>)>,
+    // Title -> reason
+    pub failures: Vec<(Title, String)>,
+    // Whether the layers were calculated with category edges. Missing from checkpoints written before the option existed
+    #[serde(default)]
+    pub category_edges: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FrontierCheckpoint {
+    // Links found so far for the new layer
+    pub layer: Vec<Title>,
+    // Titles of the last completed layer that no longer need to be retrieved
+    pub expanded: Vec<Title>,
+}
+
+impl Checkpoint {
+    pub fn load(path: &Path) -> Result<Self, CheckpointError> {
+        let bytes = fs::read(path)?;
+        Ok(serde_json::from_slice(&bytes)?)
+    }
+
+    // Writes to a temporary file first so a crash while saving never corrupts the previous checkpoint. Each save has its
+    // own so two saves of the same file can't rename each other's partial file
+    pub fn save(&self, path: &Path) -> Result<(), CheckpointError> {
+        let temp_number = TEMP_FILE_COUNTER.fetch_add(1, Ordering::Relaxed);
+        let mut temp_path = path.as_os_str().to_owned();
+        temp_path.push(format!(".{}-{}.tmp", process::id(), temp_number));
+        fs::write(&temp_path, serde_json::to_vec(self)?)?;
+        fs::rename(&temp_path, path)?;
+        Ok(())
+    }
+
+    pub fn get_start(&self) -> Option<&Title> {
+        self.layers.first().and_then(|layer| layer.first())
+    }
+}
+
+#[derive(Debug)]
+pub enum CheckpointError {
+    IoError(io::Error),
+    SerializeError(serde_json::Error),
+}
+
+impl fmt::Display for CheckpointError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::IoError(e) => write!(f, "Failed to access checkpoint file: {}", e),
+            Self::SerializeError(e) => write!(f, "Failed to serialize checkpoint: {}", e),
+        }
+    }
+}
+
+impl From<io::Error> for CheckpointError {
+    fn from(e: io::Error) -> CheckpointError {
+        CheckpointError::IoError(e)
+    }
+}
+
+impl From<serde_json::Error> for CheckpointError {
+    fn from(e: serde_json::Error) -> CheckpointError {
+        CheckpointError::SerializeError(e)
+    }
+}
+
+impl Error for CheckpointError {}
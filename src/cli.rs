@@ -0,0 +1,558 @@
+use std::error::Error;
+use std::fmt;
+use std::num::{NonZeroU32, NonZeroU64, NonZeroUsize};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Duration;
+
+use clap::error::ErrorKind;
+use clap::{Args, CommandFactory, Parser, Subcommand};
+use log::LevelFilter;
+
+use wiki_utils::cache::{ArticleCache, CacheMode};
+use wiki_utils::client::{AsyncClient, AsyncClientBuilder, LinkBackend};
+use wiki_utils::export::GraphFormat;
+use wiki_utils::fixture::FixtureStore;
+use wiki_utils::ratelimit::MIN_REQUESTS_PER_SECOND;
+use wiki_utils::site::{Site, SiteError};
+
+use crate::config::{Config, ConfigError, HttpConfig};
+
+#[derive(Parser)]
+#[command(name = "wiki-utils", version, about = "Explores the links between wiki articles")]
+pub struct Cli {
+    #[command(flatten)]
+    pub global: GlobalOptions,
+    #[command(subcommand)]
+    pub command: Command,
+}
+
+impl Cli {
+    // Exits with a usage error if the options contradict each other
+    pub fn parse_and_validate() -> Self {
+        let cli = Self::parse();
+        if let Err(e) = cli.validate() {
+            Self::command().error(ErrorKind::ArgumentConflict, e).exit();
+        }
+        cli
+    }
+
+    fn validate(&self) -> Result<(), ArgumentError> {
+        if self.global.offline && self.global.cache.is_none() {
+            return Err(ArgumentError::OfflineWithoutCache);
+        }
+        match &self.command {
+            Command::Neighbors(args) => args.crawl.validate(),
+            Command::Export(args) => args.crawl.validate(),
+            Command::Path(_) | Command::Random(_) | Command::Inspect(_) | Command::Categories(_) => Ok(()),
+        }
+    }
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    #[command(about = "Calculate every article within a number of links of a starting article")]
+    Neighbors(NeighborsArgs),
+    #[command(about = "Find the shortest chain of links between two articles")]
+    Path(PathArgs),
+    #[command(about = "Print the titles of random articles")]
+    Random(RandomArgs),
+    #[command(about = "Calculate neighbors and export the link graph for Graphviz, Gephi or similar tools")]
+    Export(ExportArgs),
+    #[command(about = "Print the title, lead and links of a single article")]
+    Inspect(InspectArgs),
+    #[command(about = "List every page under a category down to a number of subcategory levels")]
+    Categories(CategoriesArgs),
+}
+
+// Options shared by every subcommand
+#[derive(Args)]
+#[command(next_help_heading = "Global options")]
+pub struct GlobalOptions {
+    #[arg(
+        long,
+        global = true,
+        default_value = "en",
+        value_parser = parse_site,
+        help = "Wiki to use: a language code like 'de', a project like 'en.wiktionary', a host or a URL"
+    )]
+    pub site: Site,
+    #[arg(long, global = true, help = "Path of the Action API on the site [default: /w/api.php]")]
+    pub api_path: Option<String>,
+    #[arg(long, global = true, default_value = "html", value_parser = parse_backend, help = "How links are retrieved: html or api")]
+    pub backend: LinkBackend,
+    #[arg(long, global = true, value_parser = parse_concurrency, help = "Maximum number of requests in flight and articles or batches retrieved at once")]
+    pub concurrency: Option<NonZeroUsize>,
+    #[arg(long, global = true, value_parser = parse_rate_limit, help = "Maximum number of requests started per second")]
+    pub rate_limit: Option<f64>,
+    #[arg(
+        long,
+        global = true,
+        requires = "rate_limit",
+        value_parser = parse_burst,
+        help = "Requests that can start at once before --rate-limit applies [default: the rate limit rounded up]"
+    )]
+    pub burst: Option<NonZeroU32>,
+    #[arg(long, global = true, default_value = "info", value_parser = parse_log_level, help = "Terminal log level")]
+    pub log_level: LevelFilter,
+    #[arg(long, global = true, help = "Directory to cache retrieved articles in")]
+    pub cache: Option<PathBuf>,
+    #[arg(long, global = true, default_value = "86400", value_parser = parse_cache_ttl, help = "Seconds before a cached article is revalidated")]
+    pub cache_ttl: Duration,
+    #[arg(long, global = true, help = "Only serve articles from the cache")]
+    pub offline: bool,
+    #[arg(
+        long,
+        global = true,
+        conflicts_with = "replay",
+        help = "Directory to save every response to for later --replay"
+    )]
+    pub record: Option<PathBuf>,
+    #[arg(
+        long,
+        global = true,
+        help = "Directory of responses saved with --record to serve instead of making requests"
+    )]
+    pub replay: Option<PathBuf>,
+    #[command(flatten)]
+    pub http: HttpOptions,
+}
+
+// Override the [http] section of the --config file
+#[derive(Args)]
+#[command(next_help_heading = "HTTP options")]
+pub struct HttpOptions {
+    #[arg(long, global = true, help = "TOML file with an [http] section setting any of the options below")]
+    pub config: Option<PathBuf>,
+    #[arg(long, global = true, help = "Replace the whole User-Agent header")]
+    pub user_agent: Option<String>,
+    #[arg(
+        long,
+        global = true,
+        help = "Email address or URL to put in the User-Agent so site operators can reach you"
+    )]
+    pub contact: Option<String>,
+    #[arg(long, global = true, value_parser = parse_timeout, help = "Seconds to wait for a connection")]
+    pub connect_timeout: Option<Duration>,
+    #[arg(long, global = true, value_parser = parse_timeout, help = "Seconds to wait for each read of a response")]
+    pub read_timeout: Option<Duration>,
+    #[arg(long, global = true, help = "Proxy URL for all requests")]
+    pub proxy: Option<String>,
+    #[arg(long, global = true, help = "Don't accept gzip compressed responses")]
+    pub no_gzip: bool,
+    #[arg(long, global = true, help = "Don't accept brotli compressed responses")]
+    pub no_brotli: bool,
+    #[arg(long = "header", global = true, value_parser = parse_header, help = "Extra header to send as 'Name: value'. Can be repeated")]
+    pub headers: Vec<(String, String)>,
+    #[arg(
+        long = "root-certificate",
+        global = true,
+        help = "PEM file of certificates to trust. Can be repeated"
+    )]
+    pub root_certificates: Vec<PathBuf>,
+    #[arg(
+        long,
+        global = true,
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true",
+        help = "Accept invalid TLS certificates. Only for testing. '=false' overrides the config file"
+    )]
+    pub accept_invalid_certs: Option<bool>,
+}
+
+impl HttpOptions {
+    // Flags replace single values from the config file while headers and certificates are added to the file's
+    pub fn configure(&self, mut builder: AsyncClientBuilder) -> Result<AsyncClientBuilder, ConfigError> {
+        let config = match &self.config {
+            Some(path) => Config::load(path)?.http,
+            None => HttpConfig::default(),
+        };
+
+        // The User-Agent and contact are replaced together, so a --contact flag is not hidden by a user_agent in the file
+        let (user_agent, contact) = match (&self.user_agent, &self.contact) {
+            (None, None) => (config.user_agent.as_ref(), config.contact.as_ref()),
+            (user_agent, contact) => (user_agent.as_ref(), contact.as_ref()),
+        };
+        if let Some(user_agent) = user_agent {
+            builder = builder.with_user_agent(user_agent);
+        }
+        if let Some(contact) = contact {
+            builder = builder.with_contact(contact);
+        }
+        let seconds = |timeout: NonZeroU64| Duration::from_secs(timeout.get());
+        if let Some(timeout) = self.connect_timeout.or(config.connect_timeout.map(seconds)) {
+            builder = builder.with_connect_timeout(timeout);
+        }
+        if let Some(timeout) = self.read_timeout.or(config.read_timeout.map(seconds)) {
+            builder = builder.with_read_timeout(timeout);
+        }
+        if let Some(proxy) = self.proxy.as_ref().or(config.proxy.as_ref()) {
+            builder = builder.with_proxy(proxy);
+        }
+        let gzip = !self.no_gzip && config.gzip.unwrap_or(true);
+        let brotli = !self.no_brotli && config.brotli.unwrap_or(true);
+        builder = builder.with_compression(gzip, brotli);
+        for (name, value) in config.headers.iter().chain(self.headers.iter().map(|(name, value)| (name, value))) {
+            builder = builder.with_header(name, value);
+        }
+        for path in config.root_certificates.iter().chain(&self.root_certificates) {
+            builder = builder.with_root_certificate(path);
+        }
+        builder = builder.with_accept_invalid_certs(self.accept_invalid_certs.or(config.accept_invalid_certs).unwrap_or(false));
+        Ok(builder)
+    }
+}
+
+impl GlobalOptions {
+    pub fn get_site(&self) -> Site {
+        match &self.api_path {
+            Some(api_path) => self.site.clone().with_api_path(api_path),
+            None => self.site.clone(),
+        }
+    }
+
+    pub fn get_cache(&self) -> Result<Option<ArticleCache>, ArgumentError> {
+        match (&self.cache, self.offline) {
+            (Some(dir), _) => {
+                let mut cache = ArticleCache::new(dir, self.cache_ttl);
+                if self.offline {
+                    cache.set_mode(CacheMode::Offline);
+                }
+                Ok(Some(cache))
+            }
+            (None, true) => Err(ArgumentError::OfflineWithoutCache),
+            (None, false) => Ok(None),
+        }
+    }
+
+    pub fn build_client(&self) -> Result<AsyncClient, Box<dyn Error>> {
+        let mut builder = AsyncClient::builder().with_site(self.get_site()).with_link_backend(self.backend);
+        builder = self.http.configure(builder)?;
+        if let Some(cache) = self.get_cache()? {
+            builder = builder.with_cache(cache);
+        }
+        if let Some(directory) = &self.record {
+            builder = builder.with_fixtures(FixtureStore::recorder(directory));
+        }
+        if let Some(directory) = &self.replay {
+            builder = builder.with_fixtures(FixtureStore::replayer(directory));
+        }
+        if let Some(requests_per_second) = self.rate_limit {
+            let burst = self.burst.map_or(requests_per_second.ceil() as u32, NonZeroU32::get);
+            builder = builder.with_rate_limit(requests_per_second, burst);
+        }
+        if let Some(concurrency) = self.concurrency {
+            builder = builder.with_max_connections(concurrency.get());
+        }
+        Ok(builder.build()?)
+    }
+}
+
+// Where LinkCalculator gets its links from and how it saves its progress
+#[derive(Args)]
+pub struct CrawlArgs {
+    #[arg(help = "Title of the starting article")]
+    pub article: String,
+    #[arg(value_parser = parse_layer_count, help = "Number of layers of neighbors to calculate")]
+    pub layers: NonZeroUsize,
+    #[arg(long, help = "Crawl a local pages-articles XML dump (optionally .bz2) instead of the site")]
+    pub dump: Option<PathBuf>,
+    #[arg(
+        long,
+        help = "Directory containing the page, pagelinks, linktarget and redirect SQL dumps to import"
+    )]
+    pub sql_dump: Option<PathBuf>,
+    #[arg(long, help = "Link graph file to load, or to save the imported --sql-dump to")]
+    pub link_graph: Option<PathBuf>,
+    #[arg(long, help = "File to periodically save progress to")]
+    pub checkpoint: Option<PathBuf>,
+    #[arg(long, default_value = "60", value_parser = parse_checkpoint_interval, help = "Seconds between checkpoints")]
+    pub checkpoint_interval: Duration,
+    #[arg(long, help = "Continue from the --checkpoint file if it exists")]
+    pub resume: bool,
+    #[arg(
+        long,
+        help = "Also treat the categories of an article and the members of a category as its neighbors"
+    )]
+    pub category_edges: bool,
+}
+
+impl CrawlArgs {
+    fn validate(&self) -> Result<(), ArgumentError> {
+        if self.dump.is_some() && (self.sql_dump.is_some() || self.link_graph.is_some()) {
+            return Err(ArgumentError::ConflictingSources);
+        }
+        if self.resume && self.checkpoint.is_none() {
+            return Err(ArgumentError::ResumeWithoutCheckpoint);
+        }
+        Ok(())
+    }
+}
+
+#[derive(Args)]
+pub struct NeighborsArgs {
+    #[command(flatten)]
+    pub crawl: CrawlArgs,
+    #[arg(long, default_value = "text", value_parser = parse_output_format, help = "One of text, json, ndjson, dot, graphml or gexf")]
+    pub format: OutputFormat,
+    #[arg(short, long, help = "File to write to [default: <title>.<format>]")]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct ExportArgs {
+    #[command(flatten)]
+    pub crawl: CrawlArgs,
+    #[arg(long, default_value = "dot", value_parser = parse_graph_format, help = "One of dot, graphml or gexf")]
+    pub format: GraphFormat,
+    #[arg(short, long, help = "File to write to [default: <title>.<format>]")]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct PathArgs {
+    #[arg(help = "Title of the article to start from")]
+    pub source: String,
+    #[arg(help = "Title of the article to reach")]
+    pub target: String,
+    #[arg(long, default_value = "6", help = "Give up if the articles are further apart than this")]
+    pub max_hops: usize,
+    #[arg(long, default_value = "text", value_parser = parse_print_format, help = "One of text or json")]
+    pub format: PrintFormat,
+    #[arg(short, long, help = "File to write to [default: stdout]")]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct RandomArgs {
+    #[arg(long, default_value = "1", value_parser = parse_count, help = "Number of articles")]
+    pub count: NonZeroUsize,
+    #[arg(long, default_value = "text", value_parser = parse_print_format, help = "One of text or json")]
+    pub format: PrintFormat,
+    #[arg(short, long, help = "File to write to [default: stdout]")]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct InspectArgs {
+    #[arg(help = "Title of the article")]
+    pub article: String,
+    #[arg(long, help = "List every link on the article instead of only counting them")]
+    pub links: bool,
+    #[arg(long, default_value = "text", value_parser = parse_print_format, help = "One of text or json")]
+    pub format: PrintFormat,
+    #[arg(short, long, help = "File to write to [default: stdout]")]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Args)]
+pub struct CategoriesArgs {
+    #[arg(help = "Title of the category, with or without the 'Category:' prefix")]
+    pub category: String,
+    #[arg(help = "Levels of subcategories to descend into. 0 only lists the pages directly in the category")]
+    pub depth: usize,
+    #[arg(long, default_value = "text", value_parser = parse_print_format, help = "One of text or json")]
+    pub format: PrintFormat,
+    #[arg(short, long, help = "File to write to [default: stdout]")]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum OutputFormat {
+    Text,
+    Json,
+    Ndjson,
+    Graph(GraphFormat),
+}
+
+impl OutputFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Text => "txt",
+            Self::Json => "json",
+            Self::Ndjson => "ndjson",
+            Self::Graph(graph_format) => graph_format.extension(),
+        }
+    }
+}
+
+impl FromStr for OutputFormat {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" | "txt" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            "ndjson" | "jsonl" => Ok(Self::Ndjson),
+            _ => GraphFormat::from_str(s).map(Self::Graph).map_err(|_| ()),
+        }
+    }
+}
+
+// Format of the subcommands that print a small result rather than a crawl
+#[derive(Debug, Clone, Copy)]
+pub enum PrintFormat {
+    Text,
+    Json,
+}
+
+fn parse_site(arg: &str) -> Result<Site, ArgumentError> {
+    Site::from_str(arg).map_err(ArgumentError::InvalidSite)
+}
+
+fn parse_backend(arg: &str) -> Result<LinkBackend, ArgumentError> {
+    match arg {
+        "html" => Ok(LinkBackend::Html),
+        "api" => Ok(LinkBackend::Api),
+        _ => Err(ArgumentError::InvalidBackend(arg.to_string())),
+    }
+}
+
+fn parse_concurrency(arg: &str) -> Result<NonZeroUsize, ArgumentError> {
+    NonZeroUsize::from_str(arg).map_err(|_| ArgumentError::InvalidConcurrency(arg.to_string()))
+}
+
+fn parse_timeout(arg: &str) -> Result<Duration, ArgumentError> {
+    let seconds = NonZeroU64::from_str(arg).map_err(|_| ArgumentError::InvalidTimeout(arg.to_string()))?;
+    Ok(Duration::from_secs(seconds.get()))
+}
+
+fn parse_header(arg: &str) -> Result<(String, String), ArgumentError> {
+    match arg.split_once(':') {
+        Some((name, value)) if !name.trim().is_empty() => Ok((name.trim().to_string(), value.trim().to_string())),
+        _ => Err(ArgumentError::InvalidHeader(arg.to_string())),
+    }
+}
+
+fn parse_rate_limit(arg: &str) -> Result<f64, ArgumentError> {
+    match f64::from_str(arg) {
+        Ok(rate) if rate.is_finite() && rate >= MIN_REQUESTS_PER_SECOND => Ok(rate),
+        _ => Err(ArgumentError::InvalidRateLimit(arg.to_string())),
+    }
+}
+
+fn parse_burst(arg: &str) -> Result<NonZeroU32, ArgumentError> {
+    NonZeroU32::from_str(arg).map_err(|_| ArgumentError::InvalidBurst(arg.to_string()))
+}
+
+fn parse_log_level(arg: &str) -> Result<LevelFilter, ArgumentError> {
+    LevelFilter::from_str(arg).map_err(|_| ArgumentError::InvalidLogLevel(arg.to_string()))
+}
+
+fn parse_cache_ttl(arg: &str) -> Result<Duration, ArgumentError> {
+    let seconds = u64::from_str(arg).map_err(|_| ArgumentError::InvalidCacheTtl(arg.to_string()))?;
+    Ok(Duration::from_secs(seconds))
+}
+
+fn parse_layer_count(arg: &str) -> Result<NonZeroUsize, ArgumentError> {
+    NonZeroUsize::from_str(arg).map_err(|_| ArgumentError::InvalidLayerCount(arg.to_string()))
+}
+
+fn parse_count(arg: &str) -> Result<NonZeroUsize, ArgumentError> {
+    NonZeroUsize::from_str(arg).map_err(|_| ArgumentError::InvalidCount(arg.to_string()))
+}
+
+fn parse_checkpoint_interval(arg: &str) -> Result<Duration, ArgumentError> {
+    let seconds = NonZeroU64::from_str(arg).map_err(|_| ArgumentError::InvalidCheckpointInterval(arg.to_string()))?;
+    Ok(Duration::from_secs(seconds.get()))
+}
+
+fn parse_output_format(arg: &str) -> Result<OutputFormat, ArgumentError> {
+    OutputFormat::from_str(arg).map_err(|_| ArgumentError::InvalidFormat(arg.to_string()))
+}
+
+fn parse_graph_format(arg: &str) -> Result<GraphFormat, ArgumentError> {
+    GraphFormat::from_str(arg).map_err(|_| ArgumentError::InvalidGraphFormat(arg.to_string()))
+}
+
+fn parse_print_format(arg: &str) -> Result<PrintFormat, ArgumentError> {
+    match arg {
+        "text" | "txt" => Ok(PrintFormat::Text),
+        "json" => Ok(PrintFormat::Json),
+        _ => Err(ArgumentError::InvalidPrintFormat(arg.to_string())),
+    }
+}
+
+#[derive(Debug)]
+pub enum ArgumentError {
+    InvalidLayerCount(String),
+    InvalidCount(String),
+    InvalidFormat(String),
+    InvalidGraphFormat(String),
+    InvalidPrintFormat(String),
+    InvalidBackend(String),
+    InvalidSite(SiteError),
+    InvalidConcurrency(String),
+    InvalidRateLimit(String),
+    InvalidBurst(String),
+    InvalidLogLevel(String),
+    InvalidTimeout(String),
+    InvalidHeader(String),
+    InvalidCacheTtl(String),
+    OfflineWithoutCache,
+    InvalidCheckpointInterval(String),
+    ResumeWithoutCheckpoint,
+    CheckpointMismatch(String),
+    NotInDump(String),
+    ConflictingSources,
+}
+
+impl fmt::Display for ArgumentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidLayerCount(arg) => write!(
+                f,
+                "'{}' is not a valid layer count: Must be a nonzero unsigned {}-bit integer",
+                arg,
+                usize::BITS,
+            ),
+            Self::InvalidCount(arg) => write!(
+                f,
+                "'{}' is not a valid count: Must be a nonzero unsigned {}-bit integer",
+                arg,
+                usize::BITS
+            ),
+            Self::InvalidFormat(arg) => write!(
+                f,
+                "'{}' is not a valid format: Must be one of text, json, ndjson, dot, graphml or gexf",
+                arg
+            ),
+            Self::InvalidGraphFormat(arg) => write!(f, "'{}' is not a valid graph format: Must be one of dot, graphml or gexf", arg),
+            Self::InvalidPrintFormat(arg) => write!(f, "'{}' is not a valid format: Must be one of text or json", arg),
+            Self::InvalidBackend(arg) => write!(f, "'{}' is not a valid backend: Must be one of html or api", arg),
+            Self::InvalidSite(e) => write!(f, "Invalid site: {}", e),
+            Self::InvalidConcurrency(arg) => write!(f, "'{}' is not a valid concurrency: Must be a nonzero unsigned integer", arg),
+            Self::InvalidRateLimit(arg) => {
+                write!(
+                    f,
+                    "'{}' is not a valid rate limit: Must be a number of requests per second of at least one a day",
+                    arg
+                )
+            }
+            Self::InvalidBurst(arg) => write!(f, "'{}' is not a valid burst: Must be a nonzero unsigned 32-bit integer", arg),
+            Self::InvalidTimeout(arg) => write!(f, "'{}' is not a valid timeout: Must be a nonzero number of seconds", arg),
+            Self::InvalidHeader(arg) => write!(f, "'{}' is not a valid header: Must be 'Name: value'", arg),
+            Self::InvalidLogLevel(arg) => write!(
+                f,
+                "'{}' is not a valid log level: Must be one of off, error, warn, info, debug or trace",
+                arg
+            ),
+            Self::InvalidCacheTtl(arg) => write!(f, "'{}' is not a valid cache TTL: Must be a number of seconds", arg),
+            Self::OfflineWithoutCache => write!(f, "--offline requires a --cache directory"),
+            Self::InvalidCheckpointInterval(arg) => {
+                write!(
+                    f,
+                    "'{}' is not a valid checkpoint interval: Must be a nonzero number of seconds",
+                    arg
+                )
+            }
+            Self::ResumeWithoutCheckpoint => write!(f, "--resume requires a --checkpoint file"),
+            Self::CheckpointMismatch(start) => write!(f, "Checkpoint was created for a different starting article: {}", start),
+            Self::NotInDump(article) => write!(f, "'{}' is not an article in the dump", article),
+            Self::ConflictingSources => write!(f, "--dump cannot be combined with --sql-dump or --link-graph"),
+        }
+    }
+}
+
+impl Error for ArgumentError {}
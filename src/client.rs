@@ -1,29 +1,70 @@
+use std::collections::{HashMap, HashSet};
 use std::error::Error;
 use std::fmt;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Mutex, PoisonError};
 
-use reqwest::{Client, Response, StatusCode};
+use async_trait::async_trait;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::{Certificate, Client, Proxy, Response, StatusCode, Url};
 use scraper::Html;
 use tokio::sync::AcquireError;
 use tokio::sync::Semaphore;
 use tokio::time;
-use tokio::time::Duration;
+use tokio::time::{Duration, Instant};
 
-use log::{debug, trace};
+use log::{debug, error, trace};
 
-use crate::article::Article;
+use crate::api::{MergedQuery, QueryResponse, MAX_TITLES_PER_QUERY};
+use crate::article::{Article, ArticleError};
+use crate::cache::{extract_revision_id, ArticleCache, CacheEntry, CacheLookup, CacheMode, CachedContent, ContentKind};
+use crate::fixture::{Fixture, FixtureError, FixtureMode, FixtureStore};
+use crate::ratelimit::RateLimiter;
+use crate::retry::RetryPolicy;
+use crate::site::Site;
+use crate::source::{get_article_links_one_by_one, page_links_from_article, ArticleSource, PageLinks, PageLinksBatch};
+use crate::title::{Title, CATEGORY_NAMESPACE};
+use crate::url::{decode_url_str, encode_title, DecodeError};
 
-const BASE_URL: &str = "https://en.wikipedia.org/wiki/";
 const RANDOM_ARTICLE_ENDPOINT: &str = "Special:Random";
-const MAX_RETRIES: usize = 5;
-const RETRY_INTERVAL: Duration = Duration::from_millis(2000);
+const WHAT_LINKS_HERE_ENDPOINT: &str = "Special:WhatLinksHere/";
+const WHAT_LINKS_HERE_QUERY: &str = "?namespace=0&limit=5000";
 
-static CONNECTION_PERMITS: Semaphore = Semaphore::const_new(100);
+const DEFAULT_MAX_CONNECTIONS: usize = 100;
+const CLIENT_NAME: &str = concat!("wiki-utils/", env!("CARGO_PKG_VERSION"));
+const LIBRARY_NAME: &str = "reqwest/0.12";
+
+// How the links of each article are retrieved
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LinkBackend {
+    // Scrape the rendered html of each article
+    #[default]
+    Html,
+    // Query the MediaWiki Action API for many articles at once
+    Api,
+}
 
-#[derive(Default)]
 pub struct AsyncClient {
     client: Client,
-    paused: AtomicBool,
+    // Limits the requests this client has in flight. Other clients have their own permits
+    connection_permits: Semaphore,
+    // Set when the server responds with Retry-After
+    paused_until: Mutex<Option<Instant>>,
+    retry_policy: RetryPolicy,
+    // Shared by every task using this client
+    rate_limiter: Option<RateLimiter>,
+    site: Site,
+    backend: LinkBackend,
+    cache: Option<ArticleCache>,
+    fixtures: Option<FixtureStore>,
+}
+
+// The parts of a response the client reads, whether it was received or replayed
+struct FetchedPage {
+    // Url the request resolved to after redirects
+    final_url: String,
+    body: String,
 }
 
 impl AsyncClient {
@@ -31,68 +72,796 @@ impl AsyncClient {
         Self::default()
     }
 
+    pub fn with_site(site: Site) -> Self {
+        Self::builder().with_site(site).build().expect("Default http settings are valid")
+    }
+
+    pub fn builder() -> AsyncClientBuilder {
+        AsyncClientBuilder::new()
+    }
+
+    pub fn get_site(&self) -> &Site {
+        &self.site
+    }
+
     pub async fn get_article(&self, article_name: &str) -> Result<Article, ClientError> {
-        let mut url = String::from(BASE_URL);
-        url.push_str(article_name);
+        // Cached under the endpoint so every way of writing a title finds the same entry
+        let endpoint = self.to_endpoint(article_name);
+        let cache = match &self.cache {
+            Some(cache) if article_name != RANDOM_ARTICLE_ENDPOINT => {
+                if let Some(article) = self.get_cached_article(cache, &endpoint).await? {
+                    return Ok(article);
+                }
+                Some(cache)
+            }
+            Some(cache) if cache.get_mode() == CacheMode::Offline => {
+                return Err(ClientError::NotCached(article_name.to_string()));
+            }
+            _ => None,
+        };
+
+        let base_url = self.site.base_url();
+        let url = self.site.article_url(&endpoint);
         debug!("Sending request to {}", url);
 
-        let response = self.get_request(&url).await?;
+        let page = self.get_request(&url).await?;
 
-        let final_url = response.url().as_str();
-        let final_endpoint = final_url.strip_prefix(BASE_URL).ok_or(ClientError::RedirectError)?.to_owned();
+        let final_endpoint = Title::from_endpoint(page.final_url.strip_prefix(&base_url).ok_or(ClientError::RedirectError)?)?;
 
-        let response_text = response.text().await?;
+        let response_text = page.body;
         trace!("Response from {}:\n{}", final_endpoint, response_text);
-        let html = Html::parse_document(&response_text);
 
-        let article = Article::new(final_endpoint, html);
+        if let Some(cache) = cache {
+            let revision = extract_revision_id(&response_text);
+            let content = CachedContent::Html(response_text.clone());
+            let stored_endpoint = final_endpoint.get_endpoint();
+            if let Err(e) = cache
+                .store(&self.site, ContentKind::Html, &endpoint, &stored_endpoint, revision, content)
+                .await
+            {
+                error!("Failed to cache article '{}'; Reason: {}", article_name, e);
+            }
+        }
+
+        let html = Html::parse_document(&response_text);
+        let article = Article::new(final_endpoint, html).with_article_path(self.site.get_article_path());
         Ok(article)
     }
 
+    // Article names are titles as a user would type them and are never decoded. Endpoints taken from links are turned
+    // into titles before they get here
+    fn to_endpoint(&self, article_name: &str) -> String {
+        encode_title(article_name, self.site.get_capital_links())
+    }
+
+    // Stale entries are only served if their revision is still the latest. If the revision can't be checked the article
+    // is retrieved again
+    async fn get_cached_article(&self, cache: &ArticleCache, endpoint: &str) -> Result<Option<Article>, ClientError> {
+        let entry = match cache.lookup(&self.site, ContentKind::Html, endpoint).await {
+            CacheLookup::Fresh(entry) => entry,
+            CacheLookup::Stale(entry) => {
+                let latest = match entry.revision {
+                    Some(_) => self.get_latest_revision(&entry.endpoint).await.unwrap_or_else(|e| {
+                        error!("Failed to check the revision of cached article '{}'; Reason: {}", endpoint, e);
+                        None
+                    }),
+                    None => None,
+                };
+                if latest.is_none() || latest != entry.revision {
+                    debug!("Cached article '{}' is out of date", endpoint);
+                    return Ok(None);
+                }
+                debug!("Cached article '{}' is still at revision {:?}", endpoint, latest);
+                if let Err(e) = cache.touch(&self.site, ContentKind::Html, endpoint, entry.clone()).await {
+                    error!("Failed to refresh cache entry of '{}'; Reason: {}", endpoint, e);
+                }
+                entry
+            }
+            CacheLookup::Miss if cache.get_mode() == CacheMode::Offline => {
+                return Err(ClientError::NotCached(endpoint.to_string()));
+            }
+            CacheLookup::Miss => return Ok(None),
+        };
+
+        match entry.content {
+            CachedContent::Html(html) => {
+                let article = Article::new(Title::from_endpoint(&entry.endpoint)?, Html::parse_document(&html));
+                Ok(Some(article.with_article_path(self.site.get_article_path())))
+            }
+            CachedContent::Links(_) | CachedContent::PageLinks { .. } if cache.get_mode() == CacheMode::Offline => {
+                Err(ClientError::NotCached(endpoint.to_string()))
+            }
+            CachedContent::Links(_) | CachedContent::PageLinks { .. } => Ok(None),
+        }
+    }
+
     pub async fn get_random_article(&self) -> Result<Article, ClientError> {
         self.get_article(RANDOM_ARTICLE_ENDPOINT).await
     }
 
-    async fn get_request(&self, url: &str) -> Result<Response, ClientError> {
-        let mut retries = 0;
-        let mut last_try_result = Err(ClientError::Default);
+    // Uses 'action=query&prop=links|categories' to retrieve the article links and categories of many endpoints at once.
+    // Members of category pages need a query of their own
+    pub async fn get_api_links(&self, endpoints: &[Title]) -> Result<PageLinksBatch, ClientError> {
+        if endpoints.len() > MAX_TITLES_PER_QUERY {
+            return Err(ClientError::TooManyTitles(endpoints.len()));
+        }
 
-        loop {
-            if retries == MAX_RETRIES {
-                break;
-            }
-            retries += 1;
-            if self.paused.load(Ordering::SeqCst) {
-                last_try_result = Err(ClientError::PausedOnOtherThread);
-                debug!("GET '{}' Attempt {}: Paused on other thread", url, retries);
-                time::sleep(RETRY_INTERVAL).await;
+        let mut batch = PageLinksBatch::default();
+        let mut uncached = Vec::with_capacity(endpoints.len());
+        match &self.cache {
+            Some(cache) => {
+                let mut stale = Vec::new();
+                for endpoint in endpoints {
+                    match cache.lookup(&self.site, ContentKind::Links, &endpoint.get_endpoint()).await {
+                        CacheLookup::Fresh(entry) => match cached_page_links(endpoint, entry)? {
+                            Some(page) => batch.pages.push(page),
+                            None if cache.get_mode() == CacheMode::Offline => {
+                                batch
+                                    .failures
+                                    .push((endpoint.clone(), ClientError::NotCached(endpoint.get_endpoint())));
+                            }
+                            None => uncached.push(endpoint.clone()),
+                        },
+                        CacheLookup::Stale(entry) => stale.push((endpoint.clone(), entry)),
+                        CacheLookup::Miss if cache.get_mode() == CacheMode::Offline => {
+                            batch
+                                .failures
+                                .push((endpoint.clone(), ClientError::NotCached(endpoint.get_endpoint())));
+                        }
+                        CacheLookup::Miss => uncached.push(endpoint.clone()),
+                    }
+                }
+                if !stale.is_empty() {
+                    self.revalidate_links(cache, stale, &mut batch, &mut uncached).await?;
+                }
+            }
+            None => uncached.extend_from_slice(endpoints),
+        };
+        if uncached.is_empty() {
+            return Ok(batch);
+        }
+
+        let titles: Vec<String> = uncached.iter().map(Title::get_display_text).collect();
+        let joined_titles = titles.join("|");
+        let merged = self
+            .query_api(&[
+                ("prop", "links|categories|info"),
+                ("plnamespace", "0"),
+                ("pllimit", "max"),
+                ("clshow", "!hidden"),
+                ("cllimit", "max"),
+                ("redirects", "1"),
+                ("titles", joined_titles.as_str()),
+            ])
+            .await?;
+
+        for (endpoint, title) in uncached.into_iter().zip(titles.iter()) {
+            let page_title = match merged.resolve(title) {
+                Some(page_title) => page_title,
+                None => {
+                    batch.failures.push((endpoint, ClientError::MissingArticle));
+                    continue;
+                }
+            };
+            // The API only returns canonical titles so nothing is capitalized
+            let page_endpoint = Title::from_text(page_title, false);
+            let members = match page_endpoint.get_namespace() {
+                CATEGORY_NAMESPACE => match self.get_api_category_members(page_title).await {
+                    Ok(members) => members,
+                    Err(e) => {
+                        batch.failures.push((endpoint, e));
+                        continue;
+                    }
+                },
+                _ => Vec::new(),
+            };
+            let page = PageLinks {
+                requested: endpoint,
+                endpoint: page_endpoint,
+                links: merged.links[page_title].iter().map(|link| Title::from_text(link, false)).collect(),
+                categories: merged.categories[page_title]
+                    .iter()
+                    .map(|category| Title::from_text(category, false))
+                    .collect(),
+                members,
+            };
+            if let Some(cache) = &self.cache {
+                let revision = merged.revisions.get(page_title).copied();
+                let content = CachedContent::PageLinks {
+                    links: page.links.iter().map(Title::get_endpoint).collect(),
+                    categories: page.categories.iter().map(Title::get_endpoint).collect(),
+                    members: page.members.iter().map(Title::get_endpoint).collect(),
+                };
+                let (requested, final_endpoint) = (page.requested.get_endpoint(), page.endpoint.get_endpoint());
+                if let Err(e) = cache
+                    .store(&self.site, ContentKind::Links, &requested, &final_endpoint, revision, content)
+                    .await
+                {
+                    error!("Failed to cache links of '{}'; Reason: {}", page.requested, e);
+                }
+            }
+            batch.pages.push(page);
+        }
+        Ok(batch)
+    }
+
+    // Serves the stale link lists whose revision is still the latest with a single query and queues the rest to be
+    // retrieved again. If the revisions can't be checked every one of them is retrieved again
+    async fn revalidate_links(
+        &self,
+        cache: &ArticleCache,
+        stale: Vec<(Title, CacheEntry)>,
+        batch: &mut PageLinksBatch,
+        uncached: &mut Vec<Title>,
+    ) -> Result<(), ClientError> {
+        let final_endpoints: Vec<String> = stale.iter().map(|(_, entry)| entry.endpoint.clone()).collect();
+        let latest = self.get_latest_revisions(&final_endpoints).await.unwrap_or_else(|e| {
+            error!("Failed to check the revisions of {} cached link lists; Reason: {}", stale.len(), e);
+            vec![None; stale.len()]
+        });
+
+        for ((endpoint, entry), latest) in stale.into_iter().zip(latest) {
+            if latest.is_none() || latest != entry.revision {
+                debug!("Cached links of '{}' are out of date", endpoint);
+                uncached.push(endpoint);
                 continue;
             }
-            let permit = CONNECTION_PERMITS.acquire().await?;
-            let result = self.client.get(url).send().await?;
+            let requested = endpoint.get_endpoint();
+            match cached_page_links(&endpoint, entry.clone())? {
+                Some(page) => {
+                    if let Err(e) = cache.touch(&self.site, ContentKind::Links, &requested, entry).await {
+                        error!("Failed to refresh cache entry of '{}'; Reason: {}", endpoint, e);
+                    }
+                    batch.pages.push(page);
+                }
+                None => uncached.push(endpoint),
+            }
+        }
+        Ok(())
+    }
+
+    // Uses 'list=categorymembers' to retrieve every page and subcategory in the category, not only the first 200
+    async fn get_api_category_members(&self, category: &str) -> Result<Vec<Title>, ClientError> {
+        let merged = self
+            .query_api(&[
+                ("list", "categorymembers"),
+                ("cmtitle", category),
+                ("cmtype", "page|subcat"),
+                ("cmlimit", "max"),
+            ])
+            .await?;
+        Ok(merged.members.iter().map(|member| Title::from_text(member, false)).collect())
+    }
+
+    // Returns None if the article does not exist
+    pub async fn get_latest_revision(&self, endpoint: &str) -> Result<Option<u64>, ClientError> {
+        let endpoints = [endpoint.to_string()];
+        Ok(self.get_latest_revisions(&endpoints).await?.pop().flatten())
+    }
+
+    // Returns the latest revision of each endpoint in the same order, or None for the ones that do not exist
+    pub async fn get_latest_revisions(&self, endpoints: &[String]) -> Result<Vec<Option<u64>>, ClientError> {
+        if endpoints.len() > MAX_TITLES_PER_QUERY {
+            return Err(ClientError::TooManyTitles(endpoints.len()));
+        }
+        let titles = endpoints
+            .iter()
+            .map(|endpoint| decode_url_str(endpoint))
+            .collect::<Result<Vec<String>, _>>()?;
+        let joined_titles = titles.join("|");
+        let merged = self
+            .query_api(&[("prop", "info"), ("redirects", "1"), ("titles", joined_titles.as_str())])
+            .await?;
+        Ok(titles
+            .iter()
+            .map(|title| {
+                merged
+                    .resolve(title)
+                    .and_then(|page_title| merged.revisions.get(page_title).copied())
+            })
+            .collect())
+    }
+
+    // Sends an 'action=query' request with the given parameters, following continuations until complete
+    async fn query_api(&self, query_params: &[(&str, &str)]) -> Result<MergedQuery, ClientError> {
+        let mut merged = MergedQuery::default();
+        let mut continuation: HashMap<String, String> = HashMap::new();
+        loop {
+            let mut params = vec![("action", "query"), ("format", "json"), ("formatversion", "2")];
+            params.extend_from_slice(query_params);
+            params.extend(continuation.iter().map(|(k, v)| (k.as_str(), v.as_str())));
+            let api_url = self.site.api_url();
+            let url = Url::parse_with_params(&api_url, &params).map_err(|_| ClientError::UrlError(api_url))?;
+            debug!("Sending request to {}", url);
+
+            let response_text = self.get_request(url.as_str()).await?.body;
+            trace!("Response from {}:\n{}", url, response_text);
+            let response: QueryResponse = serde_json::from_str(&response_text)?;
+
+            if let Some(error) = response.error {
+                return Err(ClientError::ApiError(error.code, error.info));
+            }
+            merged.merge(response.query.unwrap_or_default());
+
+            match response.continuation {
+                Some(next) => continuation = next,
+                None => break,
+            }
+        }
+        Ok(merged)
+    }
+
+    // Returns the titles of articles that link to the given title, following 'Special:WhatLinksHere' through every page
+    pub async fn get_linking_endpoints(&self, endpoint: &Title) -> Result<Vec<Title>, ClientError> {
+        let page_endpoint = Title::from_endpoint(&(WHAT_LINKS_HERE_ENDPOINT.to_owned() + &endpoint.get_endpoint()))?;
+        let url = self.site.article_url(&page_endpoint.get_endpoint()) + WHAT_LINKS_HERE_QUERY;
+
+        let (mut titles, mut next_page) = {
+            let page = self.get_page_at(&page_endpoint, &url).await?;
+            (page.get_what_links_here_refs()?, page.get_next_what_links_here_ref())
+        };
+        // A page that links to one already retrieved would otherwise be followed forever
+        let mut requested = HashSet::new();
+        while let Some(href) = next_page.take().filter(|href| requested.insert(href.clone())) {
+            let page = self.get_page_at(&page_endpoint, &href).await?;
+            titles.extend(page.get_what_links_here_refs()?);
+            next_page = page.get_next_what_links_here_ref();
+        }
+
+        Ok(titles)
+    }
+
+    // Uses 'action=query&redirects=1' to find the articles many titles redirect to at once. Missing titles are returned
+    // unchanged
+    pub async fn resolve_redirects(&self, titles: &[Title]) -> Result<Vec<Title>, ClientError> {
+        let mut resolved = Vec::with_capacity(titles.len());
+        for chunk in titles.chunks(MAX_TITLES_PER_QUERY) {
+            let texts: Vec<String> = chunk.iter().map(Title::get_display_text).collect();
+            let joined_texts = texts.join("|");
+            let merged = self
+                .query_api(&[("prop", "info"), ("redirects", "1"), ("titles", joined_texts.as_str())])
+                .await?;
+            for (title, text) in chunk.iter().zip(&texts) {
+                // The API only returns canonical titles so nothing is capitalized
+                resolved.push(
+                    merged
+                        .resolve(text)
+                        .map_or_else(|| title.clone(), |page_title| Title::from_text(page_title, false)),
+                );
+            }
+        }
+        Ok(resolved)
+    }
+
+    // Retrieves a later page of a category's listing from an href like the one returned by
+    // Article::get_next_category_pages_ref. Those pages are never cached since the same href can list different members
+    // over time
+    pub async fn get_category_page(&self, category: &Title, href: &str) -> Result<Article, ClientError> {
+        self.get_page_at(category, href).await
+    }
+
+    // Follows the 'next page' links of both listings of a category, starting from the hrefs on its first page. Returns the
+    // subcategories and pages listed after the first page
+    pub async fn get_remaining_category_members(
+        &self,
+        category: &Title,
+        mut next_subcategories: Option<String>,
+        mut next_pages: Option<String>,
+    ) -> Result<(Vec<Title>, Vec<Title>), ClientError> {
+        let mut subcategories = Vec::new();
+        let mut pages = Vec::new();
+        // A listing page that links to one already retrieved would otherwise be followed forever
+        let mut requested = HashSet::new();
+        while let Some(href) = next_subcategories.take().filter(|href| requested.insert(href.clone())) {
+            let page = self.get_category_page(category, &href).await?;
+            subcategories.extend(page.get_subcategories());
+            next_subcategories = page.get_next_subcategories_ref();
+        }
+        while let Some(href) = next_pages.take().filter(|href| requested.insert(href.clone())) {
+            let page = self.get_category_page(category, &href).await?;
+            pages.extend(page.get_category_pages());
+            next_pages = page.get_next_category_pages_ref();
+        }
+        Ok((subcategories, pages))
+    }
+
+    // Retrieves the page at an absolute url or an href relative to the site without going through the cache
+    async fn get_page_at(&self, endpoint: &Title, href: &str) -> Result<Article, ClientError> {
+        let base_url = self.site.base_url();
+        let url = Url::parse(&base_url)
+            .and_then(|base| base.join(href))
+            .map_err(|_| ClientError::UrlError(href.to_string()))?;
+        debug!("Sending request to {}", url);
+
+        let response_text = self.get_request(url.as_str()).await?.body;
+        trace!("Response from {}:\n{}", url, response_text);
+
+        let article = Article::new(endpoint.clone(), Html::parse_document(&response_text));
+        Ok(article.with_article_path(self.site.get_article_path()))
+    }
+
+    async fn get_request(&self, url: &str) -> Result<FetchedPage, ClientError> {
+        if let Some(fixtures) = self.fixtures.as_ref().filter(|fixtures| fixtures.get_mode() == FixtureMode::Replay) {
+            let fixture = fixtures
+                .load(url)
+                .await?
+                .ok_or_else(|| ClientError::MissingFixture(url.to_string()))?;
+            return match StatusCode::from_u16(fixture.status) {
+                Ok(status) if status.is_client_error() || status.is_server_error() => Err(ClientError::StatusCodeError(status)),
+                _ => Ok(FetchedPage {
+                    final_url: fixture.final_url,
+                    body: fixture.body,
+                }),
+            };
+        }
+
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            self.wait_for_resume().await;
+            if let Some(rate_limiter) = &self.rate_limiter {
+                rate_limiter.acquire().await;
+            }
+
+            let permit = self.connection_permits.acquire().await?;
+            let result = self.client.get(url).send().await;
             drop(permit);
 
-            last_try_result = result.error_for_status().map_err(|e| ClientError::from(e));
-            match &last_try_result {
-                Err(e) => {
-                    debug!("GET '{}' Attempt {}: Failed with Error '{}'", url, retries, e);
-                    if e.status_code().is_some_and(|code| code == StatusCode::NOT_FOUND) {
-                        // Not going to bother trying again for 404 errors
-                        break;
+            let retry_after = match result {
+                Ok(response) if !response.status().is_client_error() && !response.status().is_server_error() => {
+                    trace!("GET '{}' Succeeded", url);
+                    return self.read_response(url, response).await;
+                }
+                Ok(response) => {
+                    let error = ClientError::StatusCodeError(response.status());
+                    debug!("GET '{}' Attempt {}: Failed with Error '{}'", url, attempt, error);
+                    if !self.should_retry(attempt, &error) {
+                        // Only the final failure is recorded so a replay fails the same way without the retries
+                        self.read_response(url, response).await?;
+                        return Err(error);
                     }
-                    self.paused.store(true, Ordering::SeqCst);
-                    time::sleep(RETRY_INTERVAL).await;
-                    debug!("Resuming from pause");
-                    self.paused.store(false, Ordering::SeqCst);
+                    self.retry_policy.get_retry_after(&response)
                 }
-                Ok(_) => {
-                    trace!("GET '{}' Succeeded", url);
-                    break;
+                Err(e) => {
+                    let error = ClientError::from(e);
+                    debug!("GET '{}' Attempt {}: Failed with Error '{}'", url, attempt, error);
+                    if !self.should_retry(attempt, &error) {
+                        return Err(error);
+                    }
+                    None
                 }
             };
+
+            let delay = self.retry_policy.get_delay(attempt, retry_after);
+            // The server asked for a break, so every request made through this client waits rather than only this one
+            if retry_after.is_some() {
+                self.pause_until(Instant::now() + delay);
+            }
+            debug!("GET '{}' Retrying in {:.3?}", url, delay);
+            time::sleep(delay).await;
+        }
+    }
+
+    fn should_retry(&self, attempt: usize, error: &ClientError) -> bool {
+        attempt < self.retry_policy.get_max_attempts() && self.retry_policy.is_retryable(error)
+    }
+
+    // Reads the body and records the response if this client is recording
+    async fn read_response(&self, url: &str, response: Response) -> Result<FetchedPage, ClientError> {
+        let status = response.status().as_u16();
+        let final_url = response.url().to_string();
+        let body = response.text().await?;
+        if let Some(fixtures) = self.fixtures.as_ref().filter(|fixtures| fixtures.get_mode() == FixtureMode::Record) {
+            let fixture = Fixture {
+                url: url.to_string(),
+                final_url: final_url.clone(),
+                status,
+                body: body.clone(),
+            };
+            if let Err(e) = fixtures.save(&fixture).await {
+                error!("Failed to record response from '{}'; Reason: {}", url, e);
+            }
+        }
+        Ok(FetchedPage { final_url, body })
+    }
+
+    fn pause_until(&self, deadline: Instant) {
+        let mut paused_until = self.paused_until.lock().unwrap_or_else(PoisonError::into_inner);
+        if paused_until.is_none_or(|current| current < deadline) {
+            *paused_until = Some(deadline);
+        }
+    }
+
+    async fn wait_for_resume(&self) {
+        let deadline = *self.paused_until.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(deadline) = deadline.filter(|deadline| *deadline > Instant::now()) {
+            debug!("Paused on other task; Resuming in {:.3?}", deadline - Instant::now());
+            time::sleep_until(deadline).await;
+        }
+    }
+}
+
+impl Default for AsyncClient {
+    fn default() -> Self {
+        Self::builder().build().expect("Default http settings are valid")
+    }
+}
+
+// Configures an AsyncClient. Everything not set uses the same defaults as AsyncClient::new
+#[derive(Debug, Clone)]
+pub struct AsyncClientBuilder {
+    site: Site,
+    backend: LinkBackend,
+    cache: Option<ArticleCache>,
+    fixtures: Option<FixtureStore>,
+    retry_policy: RetryPolicy,
+    rate_limit: Option<(f64, u32)>,
+    max_connections: usize,
+    user_agent: Option<String>,
+    contact: Option<String>,
+    connect_timeout: Option<Duration>,
+    read_timeout: Option<Duration>,
+    proxy: Option<String>,
+    gzip: bool,
+    brotli: bool,
+    headers: Vec<(String, String)>,
+    root_certificates: Vec<PathBuf>,
+    accept_invalid_certs: bool,
+}
+
+impl AsyncClientBuilder {
+    pub fn new() -> Self {
+        AsyncClientBuilder {
+            site: Site::default(),
+            backend: LinkBackend::default(),
+            cache: None,
+            fixtures: None,
+            retry_policy: RetryPolicy::default(),
+            rate_limit: None,
+            max_connections: DEFAULT_MAX_CONNECTIONS,
+            user_agent: None,
+            contact: None,
+            connect_timeout: None,
+            read_timeout: None,
+            proxy: None,
+            gzip: true,
+            brotli: true,
+            headers: Vec::new(),
+            root_certificates: Vec::new(),
+            accept_invalid_certs: false,
+        }
+    }
+
+    pub fn with_site(mut self, site: Site) -> Self {
+        self.site = site;
+        self
+    }
+
+    pub fn with_link_backend(mut self, backend: LinkBackend) -> Self {
+        self.backend = backend;
+        self
+    }
+
+    pub fn with_cache(mut self, cache: ArticleCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    // Either records every response to the store or serves every response from it
+    pub fn with_fixtures(mut self, fixtures: FixtureStore) -> Self {
+        self.fixtures = Some(fixtures);
+        self
+    }
+
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    // Applies to every request made through this client, including retries
+    pub fn with_rate_limit(mut self, requests_per_second: f64, burst: u32) -> Self {
+        self.rate_limit = Some((requests_per_second, burst));
+        self
+    }
+
+    // Maximum number of requests in flight at once. At least 1
+    pub fn with_max_connections(mut self, max_connections: usize) -> Self {
+        self.max_connections = max_connections.clamp(1, Semaphore::MAX_PERMITS);
+        self
+    }
+
+    // Replaces the whole User-Agent header, including the contact info
+    pub fn with_user_agent(mut self, user_agent: &str) -> Self {
+        self.user_agent = Some(user_agent.to_string());
+        self
+    }
+
+    // Email address or URL put in the default User-Agent as the Wikimedia User-Agent policy requires
+    pub fn with_contact(mut self, contact: &str) -> Self {
+        self.contact = Some(contact.to_string());
+        self
+    }
+
+    pub fn with_connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    // Applies to each read of the response rather than the whole request
+    pub fn with_read_timeout(mut self, timeout: Duration) -> Self {
+        self.read_timeout = Some(timeout);
+        self
+    }
+
+    // Used for both http and https requests
+    pub fn with_proxy(mut self, proxy_url: &str) -> Self {
+        self.proxy = Some(proxy_url.to_string());
+        self
+    }
+
+    pub fn with_compression(mut self, gzip: bool, brotli: bool) -> Self {
+        self.gzip = gzip;
+        self.brotli = brotli;
+        self
+    }
+
+    // Sent with every request
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    // PEM file with one or more certificates to trust in addition to the system's
+    pub fn with_root_certificate(mut self, path: impl Into<PathBuf>) -> Self {
+        self.root_certificates.push(path.into());
+        self
+    }
+
+    // Only for testing against servers with self-signed certificates
+    pub fn with_accept_invalid_certs(mut self, accept_invalid_certs: bool) -> Self {
+        self.accept_invalid_certs = accept_invalid_certs;
+        self
+    }
+
+    pub fn get_user_agent(&self) -> String {
+        match (&self.user_agent, &self.contact) {
+            (Some(user_agent), _) => user_agent.clone(),
+            (None, Some(contact)) => format!("{} ({}) {}", CLIENT_NAME, contact, LIBRARY_NAME),
+            (None, None) => format!("{} ({}) {}", CLIENT_NAME, env!("CARGO_PKG_REPOSITORY"), LIBRARY_NAME),
+        }
+    }
+
+    pub fn build(self) -> Result<AsyncClient, ClientError> {
+        let mut headers = HeaderMap::new();
+        for (name, value) in &self.headers {
+            let header_name = HeaderName::from_bytes(name.as_bytes())
+                .map_err(|_| ClientError::InvalidConfig(format!("Invalid header name '{}'", name)))?;
+            let header_value =
+                HeaderValue::from_str(value).map_err(|_| ClientError::InvalidConfig(format!("Invalid value for header '{}'", name)))?;
+            headers.append(header_name, header_value);
         }
 
-        last_try_result
+        let mut client_builder = Client::builder()
+            .user_agent(self.get_user_agent())
+            .default_headers(headers)
+            .gzip(self.gzip)
+            .brotli(self.brotli)
+            .danger_accept_invalid_certs(self.accept_invalid_certs);
+        if let Some(timeout) = self.connect_timeout {
+            client_builder = client_builder.connect_timeout(timeout);
+        }
+        if let Some(timeout) = self.read_timeout {
+            client_builder = client_builder.read_timeout(timeout);
+        }
+        if let Some(proxy_url) = &self.proxy {
+            let proxy = Proxy::all(proxy_url).map_err(|e| ClientError::InvalidConfig(format!("Invalid proxy '{}': {}", proxy_url, e)))?;
+            client_builder = client_builder.proxy(proxy);
+        }
+        for path in &self.root_certificates {
+            let pem =
+                fs::read(path).map_err(|e| ClientError::InvalidConfig(format!("Failed to read certificate {}: {}", path.display(), e)))?;
+            let certificates = Certificate::from_pem_bundle(&pem)
+                .map_err(|e| ClientError::InvalidConfig(format!("Invalid certificate {}: {}", path.display(), e)))?;
+            for certificate in certificates {
+                client_builder = client_builder.add_root_certificate(certificate);
+            }
+        }
+
+        Ok(AsyncClient {
+            client: client_builder.build()?,
+            connection_permits: Semaphore::new(self.max_connections),
+            paused_until: Mutex::new(None),
+            retry_policy: self.retry_policy,
+            rate_limiter: self
+                .rate_limit
+                .map(|(requests_per_second, burst)| RateLimiter::new(requests_per_second, burst)),
+            site: self.site,
+            backend: self.backend,
+            cache: self.cache,
+            fixtures: self.fixtures,
+        })
+    }
+}
+
+impl Default for AsyncClientBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// None for link lists cached before categories were retrieved, which have to be retrieved again
+fn cached_page_links(requested: &Title, entry: CacheEntry) -> Result<Option<PageLinks>, ClientError> {
+    match entry.content {
+        CachedContent::PageLinks {
+            links,
+            categories,
+            members,
+        } => Ok(Some(PageLinks {
+            requested: requested.clone(),
+            endpoint: Title::from_endpoint(&entry.endpoint)?,
+            links: parse_endpoints(&links)?,
+            categories: parse_endpoints(&categories)?,
+            members: parse_endpoints(&members)?,
+        })),
+        CachedContent::Html(_) | CachedContent::Links(_) => Ok(None),
+    }
+}
+
+// Titles are cached as endpoints
+fn parse_endpoints(endpoints: &[String]) -> Result<Vec<Title>, DecodeError> {
+    endpoints.iter().map(|endpoint| Title::from_endpoint(endpoint)).collect()
+}
+
+#[async_trait]
+impl ArticleSource for AsyncClient {
+    async fn get_article(&self, article_name: &str) -> Result<Article, ClientError> {
+        AsyncClient::get_article(self, article_name).await
+    }
+
+    async fn get_random_article(&self) -> Result<Article, ClientError> {
+        AsyncClient::get_random_article(self).await
+    }
+
+    // Unlike the default, reads the members on every page of a category's listing
+    async fn get_article_links(&self, endpoint: &Title) -> Result<PageLinks, ClientError> {
+        let (mut page, next_subcategories, next_pages) = {
+            let article = self.get_article(&endpoint.to_string()).await?;
+            (
+                page_links_from_article(endpoint, &article)?,
+                article.get_next_subcategories_ref(),
+                article.get_next_category_pages_ref(),
+            )
+        };
+        if page.endpoint.get_namespace() == CATEGORY_NAMESPACE {
+            let (subcategories, pages) = self
+                .get_remaining_category_members(&page.endpoint, next_subcategories, next_pages)
+                .await?;
+            page.members.extend(subcategories);
+            page.members.extend(pages);
+        }
+        Ok(page)
+    }
+
+    async fn get_article_links_batch(&self, endpoints: &[Title]) -> Result<PageLinksBatch, ClientError> {
+        match self.backend {
+            LinkBackend::Api => self.get_api_links(endpoints).await,
+            LinkBackend::Html => get_article_links_one_by_one(self, endpoints).await,
+        }
+    }
+
+    async fn resolve_redirects(&self, titles: &[Title]) -> Result<Vec<Title>, ClientError> {
+        AsyncClient::resolve_redirects(self, titles).await
+    }
+
+    async fn get_linking_endpoints(&self, endpoint: &Title) -> Result<Vec<Title>, ClientError> {
+        AsyncClient::get_linking_endpoints(self, endpoint).await
+    }
+
+    fn batch_size(&self) -> usize {
+        match self.backend {
+            LinkBackend::Html => 1,
+            LinkBackend::Api => MAX_TITLES_PER_QUERY,
+        }
     }
 }
 
@@ -103,7 +872,18 @@ pub enum ClientError {
     StatusCodeError(reqwest::StatusCode),
     RedirectError,
     SemaphoreAcquireError(AcquireError),
-    PausedOnOtherThread,
+    ArticleError(ArticleError),
+    DecodeError(DecodeError),
+    JsonError(serde_json::Error),
+    ApiError(String, String),
+    TooManyTitles(usize),
+    UrlError(String),
+    MissingArticle,
+    Unsupported(String),
+    NotCached(String),
+    InvalidConfig(String),
+    FixtureError(FixtureError),
+    MissingFixture(String),
 }
 
 impl ClientError {
@@ -123,7 +903,18 @@ impl fmt::Display for ClientError {
             Self::StatusCodeError(code) => write!(f, "Request returned status code: {}", code),
             Self::RedirectError => write!(f, "Redirected to different site"),
             Self::SemaphoreAcquireError(e) => write!(f, "Failed to acquire Semaphore: {}", e),
-            Self::PausedOnOtherThread => write!(f, "Other threads paused. Could not attempt request"),
+            Self::ArticleError(e) => write!(f, "Failed to parse response: {}", e),
+            Self::DecodeError(e) => write!(f, "Failed to decode endpoint: {}", e),
+            Self::JsonError(e) => write!(f, "Failed to parse json response: {}", e),
+            Self::ApiError(code, info) => write!(f, "API returned error '{}': {}", code, info),
+            Self::TooManyTitles(count) => write!(f, "{} titles given; At most {} allowed per query", count, MAX_TITLES_PER_QUERY),
+            Self::UrlError(url) => write!(f, "Failed to build url from '{}'", url),
+            Self::MissingArticle => write!(f, "Article does not exist"),
+            Self::Unsupported(operation) => write!(f, "Not supported by this source: {}", operation),
+            Self::NotCached(endpoint) => write!(f, "'{}' is not cached and the cache is offline", endpoint),
+            Self::InvalidConfig(reason) => write!(f, "Invalid client configuration: {}", reason),
+            Self::FixtureError(e) => write!(f, "Failed to replay response: {}", e),
+            Self::MissingFixture(url) => write!(f, "No recorded response for '{}'", url),
         }
     }
 }
@@ -143,4 +934,28 @@ impl From<AcquireError> for ClientError {
     }
 }
 
+impl From<ArticleError> for ClientError {
+    fn from(e: ArticleError) -> Self {
+        Self::ArticleError(e)
+    }
+}
+
+impl From<DecodeError> for ClientError {
+    fn from(e: DecodeError) -> Self {
+        Self::DecodeError(e)
+    }
+}
+
+impl From<serde_json::Error> for ClientError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::JsonError(e)
+    }
+}
+
+impl From<FixtureError> for ClientError {
+    fn from(e: FixtureError) -> Self {
+        Self::FixtureError(e)
+    }
+}
+
 impl Error for ClientError {}
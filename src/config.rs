@@ -0,0 +1,88 @@
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::num::NonZeroU64;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+// Settings read from the file given with --config. Command line flags take precedence over all of these
+//
+// [http]
+// contact = "me@example.org"
+// connect_timeout = 10
+// read_timeout = 30
+// proxy = "http://localhost:3128"
+// gzip = true
+// brotli = true
+// root_certificates = ["ca.pem"]
+// accept_invalid_certs = false
+//
+// [http.headers]
+// Api-User-Agent = "my-research-project"
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub http: HttpConfig,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HttpConfig {
+    pub user_agent: Option<String>,
+    pub contact: Option<String>,
+    // Seconds. Zero is rejected like it is for the flags
+    pub connect_timeout: Option<NonZeroU64>,
+    // Seconds
+    pub read_timeout: Option<NonZeroU64>,
+    pub proxy: Option<String>,
+    pub gzip: Option<bool>,
+    pub brotli: Option<bool>,
+    pub headers: BTreeMap<String, String>,
+    // Relative paths are relative to the config file
+    pub root_certificates: Vec<PathBuf>,
+    pub accept_invalid_certs: Option<bool>,
+}
+
+impl Config {
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        let text = fs::read_to_string(path)?;
+        let mut config: Config = toml::from_str(&text)?;
+        let directory = path.parent().unwrap_or(Path::new(""));
+        for certificate in config.http.root_certificates.iter_mut() {
+            *certificate = directory.join(&certificate);
+        }
+        Ok(config)
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    IoError(io::Error),
+    ParseError(toml::de::Error),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::IoError(e) => write!(f, "Failed to read config file: {}", e),
+            Self::ParseError(e) => write!(f, "Failed to parse config file: {}", e),
+        }
+    }
+}
+
+impl From<io::Error> for ConfigError {
+    fn from(e: io::Error) -> ConfigError {
+        ConfigError::IoError(e)
+    }
+}
+
+impl From<toml::de::Error> for ConfigError {
+    fn from(e: toml::de::Error) -> ConfigError {
+        ConfigError::ParseError(e)
+    }
+}
+
+impl Error for ConfigError {}
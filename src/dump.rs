@@ -0,0 +1,298 @@
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+use async_trait::async_trait;
+use bzip2::bufread::MultiBzDecoder;
+use log::{debug, info};
+use quick_xml::escape::resolve_predefined_entity;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use quick_xml::XmlVersion;
+
+use crate::article::Article;
+use crate::client::ClientError;
+use crate::source::{ArticleSource, PageLinks};
+use crate::title::{Title, MAIN_NAMESPACE};
+use crate::url::normalize_title;
+use crate::wikitext::{extract_wikilinks, parse_redirect};
+
+const DUMP_SOURCE_BATCH_SIZE: usize = 500;
+
+// A single <page> of a pages-articles dump with only the latest revision's text
+#[derive(Debug, Clone, Default)]
+pub struct DumpPage {
+    pub title: String,
+    pub namespace: i64,
+    pub redirect: Option<String>,
+    pub text: String,
+}
+
+impl DumpPage {
+    // Prefers the <redirect> element and falls back to parsing '#REDIRECT' from the text
+    pub fn get_redirect_target(&self) -> Option<String> {
+        match &self.redirect {
+            Some(target) => Some(normalize_title(target, true)),
+            None => parse_redirect(&self.text),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Field {
+    None,
+    Title,
+    Namespace,
+    Text,
+}
+
+// Streams pages out of a MediaWiki XML export one at a time
+pub struct DumpReader<R: BufRead> {
+    reader: Reader<R>,
+    buffer: Vec<u8>,
+}
+
+impl DumpReader<Box<dyn BufRead>> {
+    // Files ending in '.bz2' are decompressed while reading
+    pub fn open(path: &Path) -> Result<Self, DumpError> {
+        let file = BufReader::new(File::open(path)?);
+        let reader: Box<dyn BufRead> = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("bz2") => Box::new(BufReader::new(MultiBzDecoder::new(file))),
+            _ => Box::new(file),
+        };
+        Ok(Self::new(reader))
+    }
+}
+
+impl<R: BufRead> DumpReader<R> {
+    pub fn new(reader: R) -> Self {
+        DumpReader {
+            reader: Reader::from_reader(reader),
+            buffer: Vec::new(),
+        }
+    }
+
+    fn read_page(&mut self) -> Result<Option<DumpPage>, DumpError> {
+        let mut page: Option<DumpPage> = None;
+        let mut field = Field::None;
+        // Only the first <revision> of each page is kept. Exports with full history have more
+        let mut in_revision = false;
+        let mut revision_done = false;
+
+        loop {
+            self.buffer.clear();
+            let event = self.reader.read_event_into(&mut self.buffer)?;
+            match event {
+                Event::Start(e) => match e.name().as_ref() {
+                    "page" => page = Some(DumpPage::default()),
+                    "title" if page.is_some() => field = Field::Title,
+                    "ns" if page.is_some() => field = Field::Namespace,
+                    "revision" => in_revision = true,
+                    "text" if in_revision && !revision_done => field = Field::Text,
+                    _ => field = Field::None,
+                },
+                Event::Empty(e) => {
+                    if let ("redirect", Some(page)) = (e.name().as_ref(), page.as_mut()) {
+                        if let Some(attribute) = e.try_get_attribute("title")? {
+                            page.redirect = Some(attribute.normalized_value(XmlVersion::Implicit1_0)?.into_owned());
+                        }
+                    }
+                }
+                Event::Text(e) => {
+                    if let Some(target) = Self::field_buffer(&mut page, field) {
+                        target.push_str(&e.xml10_content());
+                    }
+                }
+                Event::GeneralRef(e) => {
+                    if let Some(target) = Self::field_buffer(&mut page, field) {
+                        if let Some(c) = e.resolve_char_ref()? {
+                            target.push(c);
+                        } else if let Some(resolved) = resolve_predefined_entity(&e) {
+                            target.push_str(resolved);
+                        }
+                    }
+                }
+                Event::CData(e) => {
+                    if let Some(target) = Self::field_buffer(&mut page, field) {
+                        target.push_str(&e.xml10_content());
+                    }
+                }
+                Event::End(e) => {
+                    match e.name().as_ref() {
+                        "page" => return Ok(page),
+                        "revision" => {
+                            in_revision = false;
+                            revision_done = true;
+                        }
+                        "ns" => {
+                            if let Some(page) = page.as_mut() {
+                                page.namespace = page
+                                    .text
+                                    .trim()
+                                    .parse()
+                                    .map_err(|_| DumpError::InvalidNamespace(page.text.clone()))?;
+                                page.text.clear();
+                            }
+                        }
+                        _ => (),
+                    };
+                    field = Field::None;
+                }
+                Event::Eof => return Ok(None),
+                _ => (),
+            }
+        }
+    }
+
+    // The namespace is collected into the text buffer until its closing tag is parsed
+    fn field_buffer(page: &mut Option<DumpPage>, field: Field) -> Option<&mut String> {
+        let page = page.as_mut()?;
+        match field {
+            Field::Title => Some(&mut page.title),
+            Field::Namespace | Field::Text => Some(&mut page.text),
+            Field::None => None,
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for DumpReader<R> {
+    type Item = Result<DumpPage, DumpError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_page().transpose()
+    }
+}
+
+// Link lists of every article in a dump, held in memory. Serves LinkCalculator without any network access
+#[derive(Debug, Default)]
+pub struct DumpSource {
+    // Article -> articles linked from it
+    links: HashMap<Title, Vec<Title>>,
+    // Redirect -> its target
+    redirects: HashMap<Title, Title>,
+}
+
+impl DumpSource {
+    pub fn open(path: &Path) -> Result<Self, DumpError> {
+        info!("Reading dump {}", path.display());
+        Self::from_pages(DumpReader::open(path)?)
+    }
+
+    // Pages outside of the article namespace are ignored
+    pub fn from_pages<I: IntoIterator<Item = Result<DumpPage, DumpError>>>(pages: I) -> Result<Self, DumpError> {
+        let mut source = DumpSource::default();
+        for page in pages {
+            let page = page?;
+            if page.namespace != MAIN_NAMESPACE {
+                continue;
+            }
+            let title = Title::new(MAIN_NAMESPACE, &normalize_title(&page.title, true).replace(' ', "_"));
+            match page.get_redirect_target() {
+                Some(target) => {
+                    source.redirects.insert(title, Title::from_text(&target, true));
+                }
+                None => {
+                    let links = extract_wikilinks(&page.text)
+                        .iter()
+                        .map(|link| Title::from_text(link, true))
+                        .collect();
+                    source.links.insert(title, links);
+                }
+            }
+            if (source.links.len() + source.redirects.len()).is_multiple_of(100_000) {
+                debug!("Read {} articles and {} redirects", source.links.len(), source.redirects.len());
+            }
+        }
+        info!("Read {} articles and {} redirects", source.links.len(), source.redirects.len());
+        source.remove_red_links();
+        Ok(source)
+    }
+
+    // Rendered pages link to missing articles through index.php rather than /wiki/, so those are never followed
+    fn remove_red_links(&mut self) {
+        let existing: HashSet<Title> = self.links.keys().chain(self.redirects.keys()).cloned().collect();
+        for links in self.links.values_mut() {
+            links.retain(|link| existing.contains(link));
+        }
+    }
+
+    pub fn get_article_count(&self) -> usize {
+        self.links.len()
+    }
+
+    // Returns the title of the article the title refers to, or None if there is no such article
+    pub fn resolve(&self, title: &Title) -> Option<&Title> {
+        let (key, _) = self.links.get_key_value(title).or_else(|| {
+            let target = self.redirects.get(title)?;
+            self.links.get_key_value(target)
+        })?;
+        Some(key)
+    }
+}
+
+#[async_trait]
+impl ArticleSource for DumpSource {
+    async fn get_article(&self, article_name: &str) -> Result<Article, ClientError> {
+        Err(ClientError::Unsupported(format!("Rendering '{}' from wikitext", article_name)))
+    }
+
+    async fn get_random_article(&self) -> Result<Article, ClientError> {
+        Err(ClientError::Unsupported(String::from("Rendering a random article from wikitext")))
+    }
+
+    async fn get_article_links(&self, endpoint: &Title) -> Result<PageLinks, ClientError> {
+        let resolved = self.resolve(endpoint).ok_or(ClientError::MissingArticle)?;
+        Ok(PageLinks {
+            requested: endpoint.clone(),
+            endpoint: resolved.clone(),
+            links: self.links[resolved].clone(),
+            categories: Vec::new(),
+            members: Vec::new(),
+        })
+    }
+
+    fn batch_size(&self) -> usize {
+        DUMP_SOURCE_BATCH_SIZE
+    }
+}
+
+#[derive(Debug)]
+pub enum DumpError {
+    IoError(io::Error),
+    XmlError(quick_xml::Error),
+    InvalidNamespace(String),
+}
+
+impl fmt::Display for DumpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::IoError(e) => write!(f, "Failed to read dump: {}", e),
+            Self::XmlError(e) => write!(f, "Failed to parse dump xml: {}", e),
+            Self::InvalidNamespace(ns) => write!(f, "'{}' is not a valid namespace number", ns),
+        }
+    }
+}
+
+impl From<io::Error> for DumpError {
+    fn from(e: io::Error) -> DumpError {
+        DumpError::IoError(e)
+    }
+}
+
+impl From<quick_xml::Error> for DumpError {
+    fn from(e: quick_xml::Error) -> DumpError {
+        DumpError::XmlError(e)
+    }
+}
+
+impl From<quick_xml::events::attributes::AttrError> for DumpError {
+    fn from(e: quick_xml::events::attributes::AttrError) -> DumpError {
+        DumpError::XmlError(e.into())
+    }
+}
+
+impl Error for DumpError {}
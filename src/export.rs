@@ -0,0 +1,198 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::io::Write;
+use std::str::FromStr;
+
+use crate::links::LinkCalcError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphFormat {
+    Dot,
+    GraphMl,
+    Gexf,
+}
+
+impl GraphFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Dot => "dot",
+            Self::GraphMl => "graphml",
+            Self::Gexf => "gexf",
+        }
+    }
+}
+
+impl FromStr for GraphFormat {
+    type Err = ExportError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "dot" | "gv" => Ok(Self::Dot),
+            "graphml" => Ok(Self::GraphMl),
+            "gexf" => Ok(Self::Gexf),
+            _ => Err(ExportError::UnknownFormat(s.to_string())),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct GraphNode {
+    pub endpoint: String,
+    pub title: String,
+    pub hop: usize,
+    // Decoded titles of the redirects that resolve to this node
+    pub aliases: Vec<String>,
+}
+
+// Nodes are ordered by hop then endpoint. Edges only reference endpoints found in the nodes
+#[derive(Debug, Clone, Default)]
+pub struct GraphSnapshot {
+    pub nodes: Vec<GraphNode>,
+    pub edges: Vec<(String, String)>,
+}
+
+impl GraphSnapshot {
+    pub fn write<W: Write>(&self, format: GraphFormat, writer: &mut W) -> Result<(), ExportError> {
+        match format {
+            GraphFormat::Dot => self.write_dot(writer)?,
+            GraphFormat::GraphMl => self.write_graphml(writer)?,
+            GraphFormat::Gexf => self.write_gexf(writer)?,
+        };
+        Ok(())
+    }
+
+    fn write_dot<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        let name = self.nodes.first().map_or("", |node| node.title.as_str());
+        writeln!(w, "digraph \"{}\" {{", escape_dot(name))?;
+        for node in self.nodes.iter() {
+            writeln!(
+                w,
+                "    \"{}\" [label=\"{}\", hop={}, aliases=\"{}\"];",
+                escape_dot(&node.endpoint),
+                escape_dot(&node.title),
+                node.hop,
+                escape_dot(&node.aliases.join("|")),
+            )?;
+        }
+        for (parent, child) in self.edges.iter() {
+            writeln!(w, "    \"{}\" -> \"{}\";", escape_dot(parent), escape_dot(child))?;
+        }
+        writeln!(w, "}}")
+    }
+
+    fn write_graphml<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        writeln!(w, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(w, r#"<graphml xmlns="http://graphml.graphdrawing.org/xmlns">"#)?;
+        writeln!(w, r#"  <key id="label" for="node" attr.name="label" attr.type="string"/>"#)?;
+        writeln!(w, r#"  <key id="endpoint" for="node" attr.name="endpoint" attr.type="string"/>"#)?;
+        writeln!(w, r#"  <key id="hop" for="node" attr.name="hop" attr.type="int"/>"#)?;
+        writeln!(w, r#"  <key id="aliases" for="node" attr.name="aliases" attr.type="string"/>"#)?;
+        writeln!(w, r#"  <graph id="G" edgedefault="directed">"#)?;
+        for (i, node) in self.nodes.iter().enumerate() {
+            writeln!(w, r#"    <node id="n{}">"#, i)?;
+            writeln!(w, r#"      <data key="label">{}</data>"#, escape_xml(&node.title))?;
+            writeln!(w, r#"      <data key="endpoint">{}</data>"#, escape_xml(&node.endpoint))?;
+            writeln!(w, r#"      <data key="hop">{}</data>"#, node.hop)?;
+            writeln!(w, r#"      <data key="aliases">{}</data>"#, escape_xml(&node.aliases.join("|")))?;
+            writeln!(w, r#"    </node>"#)?;
+        }
+        for (i, (source, target)) in self.indexed_edges().enumerate() {
+            writeln!(w, r#"    <edge id="e{}" source="n{}" target="n{}"/>"#, i, source, target)?;
+        }
+        writeln!(w, r#"  </graph>"#)?;
+        writeln!(w, r#"</graphml>"#)
+    }
+
+    fn write_gexf<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        writeln!(w, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+        writeln!(w, r#"<gexf xmlns="http://gexf.net/1.3" version="1.3">"#)?;
+        writeln!(w, r#"  <graph mode="static" defaultedgetype="directed">"#)?;
+        writeln!(w, r#"    <attributes class="node">"#)?;
+        writeln!(w, r#"      <attribute id="endpoint" title="endpoint" type="string"/>"#)?;
+        writeln!(w, r#"      <attribute id="hop" title="hop" type="integer"/>"#)?;
+        writeln!(w, r#"      <attribute id="aliases" title="aliases" type="liststring"/>"#)?;
+        writeln!(w, r#"    </attributes>"#)?;
+        writeln!(w, r#"    <nodes>"#)?;
+        for (i, node) in self.nodes.iter().enumerate() {
+            writeln!(w, r#"      <node id="n{}" label="{}">"#, i, escape_xml(&node.title))?;
+            writeln!(w, r#"        <attvalues>"#)?;
+            writeln!(w, r#"          <attvalue for="endpoint" value="{}"/>"#, escape_xml(&node.endpoint))?;
+            writeln!(w, r#"          <attvalue for="hop" value="{}"/>"#, node.hop)?;
+            writeln!(
+                w,
+                r#"          <attvalue for="aliases" value="{}"/>"#,
+                escape_xml(&node.aliases.join("|"))
+            )?;
+            writeln!(w, r#"        </attvalues>"#)?;
+            writeln!(w, r#"      </node>"#)?;
+        }
+        writeln!(w, r#"    </nodes>"#)?;
+        writeln!(w, r#"    <edges>"#)?;
+        for (i, (source, target)) in self.indexed_edges().enumerate() {
+            writeln!(w, r#"      <edge id="e{}" source="n{}" target="n{}"/>"#, i, source, target)?;
+        }
+        writeln!(w, r#"    </edges>"#)?;
+        writeln!(w, r#"  </graph>"#)?;
+        writeln!(w, r#"</gexf>"#)
+    }
+
+    // Edges as indices into the node list
+    fn indexed_edges(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        let index: HashMap<&str, usize> = self.nodes.iter().enumerate().map(|(i, node)| (node.endpoint.as_str(), i)).collect();
+        self.edges
+            .iter()
+            .filter_map(move |(parent, child)| Some((*index.get(parent.as_str())?, *index.get(child.as_str())?)))
+    }
+}
+
+fn escape_dot(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+fn escape_xml(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[derive(Debug)]
+pub enum ExportError {
+    UnknownFormat(String),
+    IoError(io::Error),
+    LinkCalcError(LinkCalcError),
+}
+
+impl fmt::Display for ExportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownFormat(format) => write!(f, "'{}' is not a known graph format", format),
+            Self::IoError(e) => write!(f, "Failed to write graph: {}", e),
+            Self::LinkCalcError(e) => write!(f, "Failed to read graph: {}", e),
+        }
+    }
+}
+
+impl From<io::Error> for ExportError {
+    fn from(e: io::Error) -> ExportError {
+        ExportError::IoError(e)
+    }
+}
+
+impl From<LinkCalcError> for ExportError {
+    fn from(e: LinkCalcError) -> ExportError {
+        ExportError::LinkCalcError(e)
+    }
+}
+
+impl Error for ExportError {}
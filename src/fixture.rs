@@ -0,0 +1,134 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use log::{debug, trace};
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+
+use crate::cache::fnv1a_hash;
+
+// Numbers the temporary files of this process so concurrent recordings never share one
+static TEMP_FILE_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FixtureMode {
+    // Send every request and save the final response
+    Record,
+    // Serve saved responses and never touch the network. Requests without a fixture are errors
+    Replay,
+}
+
+// A response as it was received. Only the parts the client reads are kept
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Fixture {
+    pub url: String,
+    // Url the request resolved to after redirects
+    pub final_url: String,
+    pub status: u16,
+    pub body: String,
+}
+
+// Stores one json file per requested url so tests can run against real responses without a network connection
+#[derive(Debug, Clone)]
+pub struct FixtureStore {
+    directory: PathBuf,
+    mode: FixtureMode,
+}
+
+impl FixtureStore {
+    pub fn new(directory: impl Into<PathBuf>, mode: FixtureMode) -> Self {
+        FixtureStore {
+            directory: directory.into(),
+            mode,
+        }
+    }
+
+    pub fn recorder(directory: impl Into<PathBuf>) -> Self {
+        Self::new(directory, FixtureMode::Record)
+    }
+
+    pub fn replayer(directory: impl Into<PathBuf>) -> Self {
+        Self::new(directory, FixtureMode::Replay)
+    }
+
+    pub fn get_mode(&self) -> FixtureMode {
+        self.mode
+    }
+
+    pub fn get_directory(&self) -> &Path {
+        &self.directory
+    }
+
+    // Returns None if no response was recorded for the url
+    pub async fn load(&self, url: &str) -> Result<Option<Fixture>, FixtureError> {
+        let path = self.path_for(url);
+        let bytes = match fs::read(&path).await {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let fixture: Fixture = serde_json::from_slice(&bytes)?;
+        if fixture.url != url {
+            debug!("Fixture key collision at {}", path.display());
+            return Ok(None);
+        }
+        trace!("Replaying {} from {}", url, path.display());
+        Ok(Some(fixture))
+    }
+
+    pub async fn save(&self, fixture: &Fixture) -> Result<(), FixtureError> {
+        let path = self.path_for(&fixture.url);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+        // Pretty printed so recorded fixtures can be reviewed and edited by hand. Each write has its own temporary file so
+        // two requests recorded at once can't rename each other's partial file
+        let temp_number = TEMP_FILE_COUNTER.fetch_add(1, Ordering::Relaxed);
+        let temp_path = path.with_extension(format!("json.{}-{}.tmp", process::id(), temp_number));
+        fs::write(&temp_path, serde_json::to_vec_pretty(fixture)?).await?;
+        fs::rename(&temp_path, &path).await?;
+        trace!("Recorded {} at {}", fixture.url, path.display());
+        Ok(())
+    }
+
+    // Grouped by host so fixtures of different sites can share a directory
+    fn path_for(&self, url: &str) -> PathBuf {
+        let host = url.split("://").nth(1).and_then(|rest| rest.split('/').next()).unwrap_or_default();
+        self.directory
+            .join(host.replace(':', "_"))
+            .join(format!("{:016x}.json", fnv1a_hash(url)))
+    }
+}
+
+#[derive(Debug)]
+pub enum FixtureError {
+    IoError(io::Error),
+    SerializeError(serde_json::Error),
+}
+
+impl fmt::Display for FixtureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::IoError(e) => write!(f, "Fixture io failed: {}", e),
+            Self::SerializeError(e) => write!(f, "Failed to serialize fixture: {}", e),
+        }
+    }
+}
+
+impl From<io::Error> for FixtureError {
+    fn from(e: io::Error) -> FixtureError {
+        FixtureError::IoError(e)
+    }
+}
+
+impl From<serde_json::Error> for FixtureError {
+    fn from(e: serde_json::Error) -> FixtureError {
+        FixtureError::SerializeError(e)
+    }
+}
+
+impl Error for FixtureError {}
@@ -1,4 +1,22 @@
+pub mod api;
 pub mod article;
+pub mod cache;
+pub mod category;
+pub mod checkpoint;
 pub mod client;
+pub mod dump;
+pub mod export;
+pub mod fixture;
 pub mod links;
+pub mod path;
+pub mod ratelimit;
+pub mod report;
+pub mod retry;
+pub mod section;
+pub mod site;
+pub mod source;
+pub mod sqldump;
+pub mod text;
+pub mod title;
 pub mod url;
+pub mod wikitext;
@@ -1,30 +1,95 @@
+use std::collections::{BTreeMap, BTreeSet};
 use std::error::Error;
 use std::fmt;
+use std::io::Write;
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, PoisonError, RwLock};
 
 use flurry::HashMap;
 use flurry::HashSet;
-use futures::future::join_all;
 use log::{debug, error, info};
-use tokio::task::JoinError;
+use tokio::sync::mpsc::UnboundedSender;
+use tokio::sync::{Mutex, Semaphore};
+use tokio::task;
+use tokio::task::{JoinError, JoinSet};
+use tokio::time;
+use tokio::time::{Duration, Instant};
 
 use crate::article::{Article, ArticleError};
+use crate::checkpoint::{Checkpoint, CheckpointError, FrontierCheckpoint};
 use crate::client::AsyncClient;
 use crate::client::ClientError;
-use crate::url::decode_url_str;
+use crate::export::{ExportError, GraphFormat, GraphNode, GraphSnapshot};
+use crate::report::{DiscoveredArticle, FailureReport, LayerReport, LinkReport, RedirectReport};
+use crate::source::{ArticleSource, PageLinks};
+use crate::title::Title;
 
-type LayerRef = Arc<HashSet<String>>;
+type LayerRef = Arc<HashSet<Title>>;
 type LayerGroupRef = Arc<RwLock<Vec<LayerRef>>>;
-type RedirectMapRef = Arc<HashMap<String, String>>;
+type RedirectMapRef = Arc<HashMap<Title, Title>>;
+// Parent -> titles linked from the parent as they appear in the parent's html, plus its categories and members when
+// category edges are enabled
+type EdgeMapRef = Arc<HashMap<Title, BTreeSet<Title>>>;
+// Child with redirects resolved -> titles that link to it. Only updated while holding the write lock so a link to a
+// redirect is never filed under the redirect once its target is known
+type ParentMapRef = Arc<RwLock<std::collections::HashMap<Title, BTreeSet<Title>>>>;
+// Title -> reason the article could not be retrieved or parsed
+type FailureMapRef = Arc<HashMap<Title, String>>;
 
-#[derive(Debug)]
 pub struct LinkCalculator {
     layers: LayerGroupRef,
     known_redirects: RedirectMapRef,
+    edges: EdgeMapRef,
+    parents: ParentMapRef,
+    failures: FailureMapRef,
+    discovery_sender: Option<UnboundedSender<DiscoveredArticle>>,
+    source: Arc<dyn ArticleSource>,
+    frontier: Option<Frontier>,
+    checkpoint: Option<(PathBuf, Duration)>,
+    // Held from taking a checkpoint until it is written, even if the calculation is dropped in between, so saves finish
+    // in the order their snapshots were taken
+    checkpoint_lock: Arc<Mutex<()>>,
+    // Maximum number of batches retrieved at once. Unlimited if None
+    max_concurrency: Option<NonZeroUsize>,
+    // Whether categories are neighbors of the articles in them and members are neighbors of their category
+    category_edges: bool,
+}
+
+// The layer currently being calculated. Kept outside of compute_next_async so it can be checkpointed and resumed
+#[derive(Clone)]
+struct Frontier {
+    this_layer: LayerRef,
+    // Endpoints of the last layer whose links have already been stored
+    expanded: LayerRef,
+}
+
+impl Frontier {
+    fn new() -> Self {
+        Frontier {
+            this_layer: LayerRef::new(HashSet::new()),
+            expanded: LayerRef::new(HashSet::new()),
+        }
+    }
+}
+
+// Everything a spawned task needs to store the links of one article into the layer being calculated
+#[derive(Clone)]
+struct LayerContext {
+    hop: usize,
+    this_layer: LayerRef,
+    expanded: LayerRef,
+    known_redirects: RedirectMapRef,
+    previous_layers: LayerGroupRef,
+    edges: EdgeMapRef,
+    parents: ParentMapRef,
+    failures: FailureMapRef,
+    discovery_sender: Option<UnboundedSender<DiscoveredArticle>>,
+    category_edges: bool,
 }
 
 impl LinkCalculator {
-    pub fn new(start_point: String) -> Self {
+    pub fn new(start_point: Title) -> Self {
         let mut layers: Vec<LayerRef> = Vec::new();
 
         let start = Self::layer_zero(start_point);
@@ -35,28 +100,57 @@ impl LinkCalculator {
         LinkCalculator {
             layers,
             known_redirects: Arc::new(HashMap::new()),
+            edges: Arc::new(HashMap::new()),
+            parents: Arc::new(RwLock::new(std::collections::HashMap::new())),
+            failures: Arc::new(HashMap::new()),
+            discovery_sender: None,
+            source: Arc::new(AsyncClient::new()),
+            frontier: None,
+            checkpoint: None,
+            checkpoint_lock: Arc::new(Mutex::new(())),
+            max_concurrency: None,
+            category_edges: false,
         }
     }
 
     pub fn from_article(first_article: &Article) -> Result<Self, ArticleError> {
-        let layer_zero: LayerRef = Self::layer_zero(first_article.get_endpoint().to_string());
+        let layer_zero: LayerRef = Self::layer_zero(first_article.get_endpoint().clone());
 
         info!("Creating layer 1 from links of given article");
         let links = first_article.get_article_link_refs()?;
 
         let layer_one = HashSet::with_capacity(links.len());
         let guard = layer_one.guard();
-        for link in links {
-            layer_one.insert(link.to_owned(), &guard);
+        for link in links.iter() {
+            layer_one.insert(link.clone(), &guard);
         }
         drop(guard);
         let layer_one = Arc::new(layer_one);
 
         let layers = Arc::new(RwLock::new(vec![layer_zero, layer_one]));
 
+        let edges: EdgeMapRef = Arc::new(HashMap::new());
+        let guard = edges.guard();
+        edges.insert(first_article.get_endpoint().clone(), links.iter().cloned().collect(), &guard);
+        drop(guard);
+
+        let known_redirects: RedirectMapRef = Arc::new(HashMap::new());
+        let parents: ParentMapRef = Arc::new(RwLock::new(std::collections::HashMap::new()));
+        Self::index_parents(&parents, &known_redirects, first_article.get_endpoint(), &links);
+
         Ok(LinkCalculator {
             layers,
-            known_redirects: Arc::new(HashMap::new()),
+            known_redirects,
+            edges,
+            parents,
+            failures: Arc::new(HashMap::new()),
+            discovery_sender: None,
+            source: Arc::new(AsyncClient::new()),
+            frontier: None,
+            checkpoint: None,
+            checkpoint_lock: Arc::new(Mutex::new(())),
+            max_concurrency: None,
+            category_edges: false,
         })
     }
 
@@ -64,50 +158,272 @@ impl LinkCalculator {
         Ok(self.layers.read()?.len())
     }
 
+    // Every article already in a layer is sent immediately. Afterwards, each article is sent as it is discovered
+    pub fn set_discovery_sender(&mut self, sender: UnboundedSender<DiscoveredArticle>) -> Result<(), LinkCalcError> {
+        let layers = self.layers.read()?;
+        for (hop, layer) in layers.iter().enumerate() {
+            let guard = layer.guard();
+            for endpoint in layer.iter(&guard) {
+                let parent = self.find_parent(&layers, endpoint, hop)?;
+                Self::send_discovery(&sender, hop, endpoint, parent.as_ref());
+            }
+        }
+        self.discovery_sender = Some(sender);
+        Ok(())
+    }
+
+    pub fn take_discovery_sender(&mut self) -> Option<UnboundedSender<DiscoveredArticle>> {
+        self.discovery_sender.take()
+    }
+
+    // Should provide the same site the starting article was retrieved from
+    pub fn set_source(&mut self, source: Arc<dyn ArticleSource>) {
+        self.source = source;
+    }
+
+    pub fn set_max_concurrency(&mut self, max_concurrency: Option<NonZeroUsize>) {
+        self.max_concurrency = max_concurrency;
+    }
+
+    // Category pages are only reached through category edges. Sources without category data add no such edges
+    pub fn set_category_edges(&mut self, category_edges: bool) {
+        self.category_edges = category_edges;
+    }
+
     pub async fn compute_next_async(&mut self) -> Result<(), LinkCalcError> {
-        info!("Calculating layer {}", self.get_layer_count()?);
-        let client = Arc::new(AsyncClient::new());
+        let hop = self.get_layer_count()?;
+        info!("Calculating layer {}", hop);
+        let source = self.source.clone();
 
         let last_layer = self.get_last_layer()?;
-        let this_layer = LayerRef::new(HashSet::new());
+        let frontier = self.frontier.get_or_insert_with(Frontier::new).clone();
+        let context = LayerContext {
+            hop,
+            this_layer: frontier.this_layer.clone(),
+            expanded: frontier.expanded.clone(),
+            known_redirects: self.known_redirects.clone(),
+            previous_layers: self.layers.clone(),
+            edges: self.edges.clone(),
+            parents: self.parents.clone(),
+            failures: self.failures.clone(),
+            discovery_sender: self.discovery_sender.clone(),
+            category_edges: self.category_edges,
+        };
 
-        let mut handles = Vec::with_capacity(last_layer.len());
         let guard = last_layer.guard();
+        let expanded_guard = frontier.expanded.guard();
+        let mut links: Vec<Title> = last_layer
+            .iter(&guard)
+            .filter(|link| !frontier.expanded.contains(*link, &expanded_guard))
+            .cloned()
+            .collect();
+        drop(expanded_guard);
+        drop(guard);
+        // Sorted so the same layer is always split into the same batches and so makes the same requests
+        links.sort();
+        if !frontier.expanded.is_empty() {
+            info!("Resuming layer {} with {} articles already retrieved", hop, frontier.expanded.len());
+        }
+
+        let batch_size = source.batch_size().max(1);
+        let permits = self.max_concurrency.map(|max| Arc::new(Semaphore::new(max.get())));
+        // Dropping the set aborts every task still running, so an interrupted calculation stops making requests
+        let mut pending = JoinSet::new();
+        for batch in links.chunks(batch_size) {
+            let batch = batch.to_vec();
+            let context_clone = context.clone();
+            let source_clone = source.clone();
+            let permits_clone = permits.clone();
+
+            pending.spawn(async move {
+                let _permit = match permits_clone {
+                    Some(permits) => Some(permits.acquire_owned().await.map_err(ClientError::from)?),
+                    None => None,
+                };
+                Self::store_batch_links(source_clone.as_ref(), batch, context_clone).await
+            });
+        }
 
-        let link_iter = last_layer.iter(&guard);
+        let mut checkpoint_timer = self
+            .checkpoint
+            .as_ref()
+            .map(|(_, interval)| time::interval_at(Instant::now() + *interval, *interval));
+        loop {
+            tokio::select! {
+                result = pending.join_next() => match result {
+                    Some(Ok(Ok(()))) => (),
+                    Some(Ok(Err(e))) => return Err(e),
+                    Some(Err(e)) => return Err(e.into()),
+                    None => break,
+                },
+                _ = async { checkpoint_timer.as_mut().expect("Checked by precondition").tick().await }, if checkpoint_timer.is_some() => {
+                    self.write_checkpoint().await;
+                }
+            }
+        }
 
-        for link_ref in link_iter {
-            let link = link_ref.to_owned();
-            let this_layer_clone = this_layer.clone();
-            let known_redirects_clone = self.known_redirects.clone();
-            let previous_layers_clone = self.layers.clone();
-            let client_clone = client.clone();
+        // Links to a redirect found before the redirect was known are stored as the redirect. Once replaced by their
+        // targets they can be titles of an earlier layer
+        Self::normalize_layer(last_layer.clone(), &self.known_redirects);
+        Self::normalize_layer(context.this_layer.clone(), &self.known_redirects);
+        {
+            let mut layers = self.layers.write()?;
+            if let Some((last, earlier)) = layers.split_last() {
+                Self::remove_duplicates(last, earlier);
+            }
+            Self::remove_duplicates(&context.this_layer, &layers);
+            layers.push(context.this_layer);
+        }
+        self.frontier = None;
+        self.write_checkpoint().await;
 
-            let handle = tokio::spawn(async move {
-                Self::store_article_links(&client_clone, link, this_layer_clone, known_redirects_clone, previous_layers_clone).await
-            });
+        Ok(())
+    }
 
-            handles.push(handle);
+    // Periodically saves the calculator's state to the given file while layers are being calculated
+    pub fn set_checkpoint_file(&mut self, path: PathBuf, interval: Duration) {
+        self.checkpoint = Some((path, interval));
+    }
+
+    // Any layer in progress will continue where it left off on the next call to compute_next_async
+    pub fn from_checkpoint(checkpoint: Checkpoint) -> Result<Self, LinkCalcError> {
+        let Checkpoint {
+            layers,
+            frontier,
+            redirects,
+            edges,
+            failures,
+            category_edges,
+        } = checkpoint;
+
+        if layers.is_empty() {
+            return Err(LinkCalcError::NotInitializedError);
         }
-        let results = join_all(handles).await;
+        let layers: Vec<LayerRef> = layers.into_iter().map(Self::layer_from_vec).collect();
 
-        let mut new_redirects = Vec::new();
-        for result in results {
-            match result {
-                Ok(Ok(thread_new_redirects)) => {
-                    new_redirects.extend(thread_new_redirects);
-                }
-                Ok(Err(e)) => return Err(e),
-                Err(e) => return Err(e.into()),
-            };
+        let known_redirects: RedirectMapRef = Arc::new(HashMap::new());
+        let guard = known_redirects.guard();
+        for (link, target) in redirects {
+            known_redirects.insert(link, target, &guard);
         }
+        drop(guard);
+
+        let edge_map: EdgeMapRef = Arc::new(HashMap::new());
+        let parents: ParentMapRef = Arc::new(RwLock::new(std::collections::HashMap::new()));
+        let guard = edge_map.guard();
+        for (parent, children) in edges {
+            Self::index_parents(&parents, &known_redirects, &parent, &children);
+            edge_map.insert(parent, children.into_iter().collect(), &guard);
+        }
+        drop(guard);
+
+        let failure_map: FailureMapRef = Arc::new(HashMap::new());
+        let guard = failure_map.guard();
+        for (endpoint, reason) in failures {
+            failure_map.insert(endpoint, reason, &guard);
+        }
+        drop(guard);
+
+        let frontier = frontier.map(|frontier| Frontier {
+            this_layer: Self::layer_from_vec(frontier.layer),
+            expanded: Self::layer_from_vec(frontier.expanded),
+        });
+
+        Ok(LinkCalculator {
+            layers: Arc::new(RwLock::new(layers)),
+            known_redirects,
+            edges: edge_map,
+            parents,
+            failures: failure_map,
+            discovery_sender: None,
+            source: Arc::new(AsyncClient::new()),
+            frontier,
+            checkpoint: None,
+            checkpoint_lock: Arc::new(Mutex::new(())),
+            max_concurrency: None,
+            category_edges,
+        })
+    }
+
+    pub fn to_checkpoint(&self) -> Result<Checkpoint, LinkCalcError> {
+        // Articles are only marked expanded once their links, redirects and failures are stored. Reading the expanded
+        // articles before anything else means the checkpoint has everything stored for each of them, even while tasks
+        // are still running
+        let expanded = self.frontier.as_ref().map(|frontier| Self::layer_to_vec(&frontier.expanded));
+        let layers = self.layers.read()?.iter().map(Self::layer_to_vec).collect();
+        let frontier = self.frontier.as_ref().zip(expanded).map(|(frontier, expanded)| FrontierCheckpoint {
+            layer: Self::layer_to_vec(&frontier.this_layer),
+            expanded,
+        });
+
+        let guard = self.known_redirects.guard();
+        let redirects = self.known_redirects.iter(&guard).map(|(k, v)| (k.clone(), v.clone())).collect();
+        drop(guard);
+
+        let guard = self.edges.guard();
+        let edges = self
+            .edges
+            .iter(&guard)
+            .map(|(k, v)| (k.clone(), v.iter().cloned().collect()))
+            .collect();
+        drop(guard);
 
-        Self::normalize_layer(last_layer.clone(), new_redirects);
-        self.layers.write()?.push(this_layer);
+        let guard = self.failures.guard();
+        let failures = self.failures.iter(&guard).map(|(k, v)| (k.clone(), v.clone())).collect();
+        drop(guard);
+
+        Ok(Checkpoint {
+            layers,
+            frontier,
+            redirects,
+            edges,
+            failures,
+            category_edges: self.category_edges,
+        })
+    }
 
+    // Waits for any save still in progress, such as one left running by an interrupted calculation, so an older
+    // checkpoint never replaces this one. The file is written on the blocking pool so large checkpoints don't stall the
+    // tasks retrieving articles
+    pub async fn save_checkpoint(&self, path: &Path) -> Result<(), LinkCalcError> {
+        let lock = self.checkpoint_lock.clone().lock_owned().await;
+        let checkpoint = self.to_checkpoint()?;
+        let path = path.to_path_buf();
+        task::spawn_blocking(move || {
+            let _lock = lock;
+            checkpoint.save(&path)
+        })
+        .await??;
         Ok(())
     }
 
+    // Checkpoint failures are logged rather than interrupting the calculation
+    async fn write_checkpoint(&self) {
+        let path = match &self.checkpoint {
+            Some((path, _)) => path,
+            None => return,
+        };
+        match self.save_checkpoint(path).await {
+            Ok(()) => debug!("Wrote checkpoint to {}", path.display()),
+            Err(e) => error!("Failed to write checkpoint to {}; Reason: {}", path.display(), e),
+        }
+    }
+
+    fn layer_to_vec(layer: &LayerRef) -> Vec<Title> {
+        let guard = layer.guard();
+        layer.iter(&guard).cloned().collect()
+    }
+
+    fn layer_from_vec(endpoints: Vec<Title>) -> LayerRef {
+        let layer = HashSet::with_capacity(endpoints.len());
+        let guard = layer.guard();
+        for endpoint in endpoints {
+            layer.insert(endpoint, &guard);
+        }
+        drop(guard);
+        Arc::new(layer)
+    }
+
     pub async fn compute_layers_async(&mut self, count: usize) -> Result<(), LinkCalcError> {
         for _ in 0..count {
             self.compute_next_async().await?;
@@ -116,70 +432,323 @@ impl LinkCalculator {
         Ok(())
     }
 
-    fn get_last_layer(&self) -> Result<LayerRef, LinkCalcError> {
-        Ok(self.layers.read()?.last().ok_or(LinkCalcError::NotInitializedError)?.clone())
+    // Returns the titles linked from the given title with redirects resolved
+    pub fn get_children(&self, endpoint: &Title) -> Vec<Title> {
+        let parent = self.resolve_redirect(endpoint);
+        let guard = self.edges.guard();
+        let children = match self.edges.get(&parent, &guard) {
+            Some(children) => children,
+            None => return Vec::new(),
+        };
+        let resolved: BTreeSet<Title> = children.iter().map(|child| self.resolve_redirect(child)).collect();
+        resolved.into_iter().collect()
     }
 
-    // Returns new article redirects
-    async fn store_article_links(
-        client: &AsyncClient,
-        link: String,
-        this_layer: LayerRef,
-        known_redirects: RedirectMapRef,
-        previous_layers: LayerGroupRef,
-    ) -> Result<Option<(String, String)>, LinkCalcError> {
-        let neighbor_article = match client.get_article(&link).await {
-            Ok(article) => article,
-            Err(e) => {
-                error!("Failed to retrieve article '{}'; Reason {}", link, e);
-                return Ok(None);
+    // Returns the titles that link to the given title with redirects resolved
+    pub fn get_parents(&self, endpoint: &Title) -> Vec<Title> {
+        let child = self.resolve_redirect(endpoint);
+        let parents = self.parents.read().unwrap_or_else(PoisonError::into_inner);
+        parents
+            .get(&child)
+            .map(|parents| parents.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    // Returns every parent -> child edge with redirects resolved
+    pub fn get_edges(&self) -> Vec<(Title, Title)> {
+        let guard = self.edges.guard();
+        let mut edges = BTreeSet::new();
+        for (parent, children) in self.edges.iter(&guard) {
+            for child in children {
+                edges.insert((parent.clone(), self.resolve_redirect(child)));
             }
+        }
+        edges.into_iter().collect()
+    }
+
+    // Returns the chain of titles from layer zero to the given title, or None if the title is in no layer
+    pub fn trace_to_start(&self, endpoint: &Title) -> Result<Option<Vec<Title>>, LinkCalcError> {
+        let endpoint = self.resolve_redirect(endpoint);
+        let layer_num = match Self::find_in_previous_layer(self.layers.clone(), self.known_redirects.clone(), &endpoint)? {
+            Some(layer_num) => layer_num,
+            None => return Ok(None),
         };
 
-        let new_redirect = match link.eq(neighbor_article.get_endpoint()) {
-            true => None,
-            false => {
-                info!("Found redirect: {} -> {}", link, neighbor_article.get_endpoint());
-                let guard = known_redirects.guard();
-                known_redirects.insert(link.to_string(), neighbor_article.get_endpoint().to_string(), &guard);
-                Some((link.to_string(), neighbor_article.get_endpoint().to_string()))
-            }
+        let layers = self.layers.read()?;
+        let mut trace = vec![endpoint];
+        for hop in (1..=layer_num).rev() {
+            let current = trace.last().expect("Trace always has at least one element");
+            let parent = self
+                .find_parent(&layers, current, hop)?
+                .ok_or_else(|| LinkCalcError::MissingParentError(current.to_string()))?;
+            trace.push(parent);
+        }
+        trace.reverse();
+
+        Ok(Some(trace))
+    }
+
+    pub fn get_report(&self) -> Result<LinkReport, LinkCalcError> {
+        let layers = self.layers.read()?;
+        let mut layer_reports = Vec::with_capacity(layers.len());
+        for (hop, layer) in layers.iter().enumerate() {
+            let guard = layer.guard();
+            let mut articles: Vec<String> = layer.iter(&guard).map(Title::to_string).collect();
+            articles.sort();
+            layer_reports.push(LayerReport { hop, articles });
+        }
+        let start = layer_reports
+            .first()
+            .and_then(|layer| layer.articles.first())
+            .cloned()
+            .unwrap_or_default();
+
+        let guard = self.known_redirects.guard();
+        let mut redirects: Vec<RedirectReport> = self
+            .known_redirects
+            .iter(&guard)
+            .map(|(link, target)| RedirectReport {
+                from: link.to_string(),
+                to: target.to_string(),
+            })
+            .collect();
+        redirects.sort_by(|a, b| a.from.cmp(&b.from));
+
+        let guard = self.failures.guard();
+        let mut failures: Vec<FailureReport> = self
+            .failures
+            .iter(&guard)
+            .map(|(endpoint, reason)| FailureReport {
+                article: endpoint.to_string(),
+                reason: reason.clone(),
+            })
+            .collect();
+        failures.sort_by(|a, b| a.article.cmp(&b.article));
+
+        Ok(LinkReport {
+            start,
+            layers: layer_reports,
+            redirects,
+            failures,
+        })
+    }
+
+    pub fn write_json<W: Write>(&self, writer: &mut W) -> Result<(), LinkCalcError> {
+        serde_json::to_writer_pretty(writer, &self.get_report()?)?;
+        Ok(())
+    }
+
+    // Finds a title in the layer before `hop` that links to the given title. Takes the layers from a read lock the caller
+    // already holds, since taking a second one can deadlock if a writer is waiting
+    fn find_parent(&self, layers: &[LayerRef], endpoint: &Title, hop: usize) -> Result<Option<Title>, LinkCalcError> {
+        if hop == 0 {
+            return Ok(None);
+        }
+        let layer = match layers.get(hop - 1) {
+            Some(layer) => layer,
+            None => return Ok(None),
         };
+        let guard = layer.guard();
+        let parents = self.parents.read()?;
+        let parent = parents
+            .get(&self.resolve_redirect(endpoint))
+            .and_then(|parents| parents.iter().find(|parent| layer.contains(*parent, &guard)));
+        Ok(parent.cloned())
+    }
 
-        let neighbor_links = match neighbor_article.get_article_link_refs() {
-            Ok(links) => links,
+    fn send_discovery(sender: &UnboundedSender<DiscoveredArticle>, hop: usize, endpoint: &Title, parent: Option<&Title>) {
+        let discovered = DiscoveredArticle {
+            hop,
+            title: endpoint.to_string(),
+            endpoint: endpoint.get_endpoint(),
+            parent: parent.map(Title::to_string),
+        };
+        if sender.send(discovered).is_err() {
+            debug!("Discovery receiver dropped; Not sending {}", endpoint);
+        }
+    }
+
+    // Returns every node with the first hop it was found at along with every edge between nodes
+    pub fn get_graph_snapshot(&self) -> Result<GraphSnapshot, LinkCalcError> {
+        let mut hops: BTreeMap<Title, usize> = BTreeMap::new();
+        for (layer_num, layer) in self.layers.read()?.iter().enumerate() {
+            let guard = layer.guard();
+            for endpoint in layer.iter(&guard) {
+                hops.entry(endpoint.clone()).or_insert(layer_num);
+            }
+        }
+
+        let mut aliases: BTreeMap<Title, Vec<String>> = BTreeMap::new();
+        let guard = self.known_redirects.guard();
+        for (link, target) in self.known_redirects.iter(&guard) {
+            aliases.entry(target.clone()).or_default().push(link.to_string());
+        }
+        drop(guard);
+
+        let mut nodes: Vec<GraphNode> = hops
+            .into_iter()
+            .map(|(endpoint, hop)| GraphNode {
+                title: endpoint.to_string(),
+                aliases: aliases.remove(&endpoint).unwrap_or_default(),
+                endpoint: endpoint.get_endpoint(),
+                hop,
+            })
+            .collect();
+        nodes.sort_by_key(|node| node.hop);
+
+        let known: std::collections::HashSet<&str> = nodes.iter().map(|node| node.endpoint.as_str()).collect();
+        let edges = self
+            .get_edges()
+            .into_iter()
+            .map(|(parent, child)| (parent.get_endpoint(), child.get_endpoint()))
+            .filter(|(parent, child)| known.contains(parent.as_str()) && known.contains(child.as_str()))
+            .collect();
+
+        Ok(GraphSnapshot { nodes, edges })
+    }
+
+    pub fn export_graph<W: Write>(&self, format: GraphFormat, writer: &mut W) -> Result<(), ExportError> {
+        self.get_graph_snapshot()?.write(format, writer)
+    }
+
+    fn resolve_redirect(&self, endpoint: &Title) -> Title {
+        let guard = self.known_redirects.guard();
+        self.known_redirects.get(endpoint, &guard).unwrap_or(endpoint).clone()
+    }
+
+    fn get_last_layer(&self) -> Result<LayerRef, LinkCalcError> {
+        Ok(self.layers.read()?.last().ok_or(LinkCalcError::NotInitializedError)?.clone())
+    }
+
+    async fn store_batch_links(source: &dyn ArticleSource, batch: Vec<Title>, context: LayerContext) -> Result<(), LinkCalcError> {
+        let pages = match source.get_article_links_batch(&batch).await {
+            Ok(page_batch) => {
+                for (link, e) in page_batch.failures {
+                    error!("Failed to retrieve links for article '{}'; Reason {}", link, e);
+                    Self::record_failure(&context.failures, link, e.to_string());
+                }
+                page_batch.pages
+            }
             Err(e) => {
-                error!("Failed to identify links for article '{}'; Reason: {}", link, e);
+                error!("Failed to retrieve links for {} articles; Reason {}", batch.len(), e);
+                for link in batch.iter() {
+                    Self::record_failure(&context.failures, link.clone(), e.to_string());
+                }
                 Vec::new()
             }
         };
 
-        for neighbor_link in neighbor_links {
-            if Self::find_in_previous_layer(previous_layers.clone(), known_redirects.clone(), neighbor_link)?.is_none() {
-                let guard = this_layer.guard();
-                this_layer.insert(neighbor_link.to_string(), &guard);
+        for page in pages {
+            Self::store_page_links(page, &context)?;
+        }
+
+        // Only marked once everything is stored so a checkpoint never skips an article with missing links
+        let guard = context.expanded.guard();
+        for link in batch {
+            context.expanded.insert(link, &guard);
+        }
+        Ok(())
+    }
+
+    fn store_page_links(page: PageLinks, context: &LayerContext) -> Result<(), LinkCalcError> {
+        let PageLinks {
+            requested,
+            endpoint,
+            mut links,
+            categories,
+            members,
+        } = page;
+        if context.category_edges {
+            links.extend(categories);
+            links.extend(members);
+        }
+
+        if requested != endpoint {
+            info!("Found redirect: {} -> {}", requested, endpoint);
+            let mut parents = context.parents.write()?;
+            let guard = context.known_redirects.guard();
+            context.known_redirects.insert(requested.clone(), endpoint.clone(), &guard);
+            // Links to the redirect stored before it was known are links to its target
+            if let Some(redirect_parents) = parents.remove(&requested) {
+                parents.entry(endpoint.clone()).or_default().extend(redirect_parents);
+            }
+        }
+
+        for neighbor_link in links.iter() {
+            if Self::find_in_previous_layer(context.previous_layers.clone(), context.known_redirects.clone(), neighbor_link)?.is_none() {
+                let guard = context.this_layer.guard();
+                let is_new = context.this_layer.insert(neighbor_link.clone(), &guard);
+                if let (true, Some(sender)) = (is_new, &context.discovery_sender) {
+                    Self::send_discovery(sender, context.hop, neighbor_link, Some(&endpoint));
+                }
             }
         }
-        debug!("Finished storing links for endpoint: {}", link);
-        Ok(new_redirect)
+
+        Self::index_parents(&context.parents, &context.known_redirects, &endpoint, &links);
+        let guard = context.edges.guard();
+        context.edges.insert(endpoint, links.into_iter().collect(), &guard);
+        debug!("Finished storing links for endpoint: {}", requested);
+        Ok(())
+    }
+
+    fn index_parents(parents: &ParentMapRef, known_redirects: &RedirectMapRef, parent: &Title, children: &[Title]) {
+        let mut parents = parents.write().unwrap_or_else(PoisonError::into_inner);
+        let guard = known_redirects.guard();
+        for child in children {
+            let child = known_redirects.get(child, &guard).unwrap_or(child);
+            parents.entry(child.clone()).or_default().insert(parent.clone());
+        }
+    }
+
+    fn record_failure(failures: &FailureMapRef, endpoint: Title, reason: String) {
+        let guard = failures.guard();
+        failures.insert(endpoint, reason, &guard);
     }
 
     // Replace redirects
-    fn normalize_layer(last_layer: LayerRef, new_redirects: Vec<(String, String)>) {
+    fn normalize_layer(last_layer: LayerRef, known_redirects: &RedirectMapRef) {
         let guard = last_layer.guard();
+        let redirects_guard = known_redirects.guard();
+        let new_redirects: Vec<(Title, Title)> = last_layer
+            .iter(&guard)
+            .filter_map(|link| {
+                known_redirects
+                    .get(link, &redirects_guard)
+                    .map(|target| (link.clone(), target.clone()))
+            })
+            .collect();
         for (link, target) in new_redirects {
             last_layer.remove(&link, &guard);
             last_layer.insert(target, &guard);
         }
     }
 
+    // Redirect targets are only added to a layer once it is normalized, so links to them found while the next layer was
+    // being calculated can end up in that layer too
+    fn remove_duplicates(layer: &LayerRef, previous_layers: &[LayerRef]) {
+        let guard = layer.guard();
+        let duplicates: Vec<Title> = layer
+            .iter(&guard)
+            .filter(|link| {
+                previous_layers.iter().any(|previous| {
+                    let previous_guard = previous.guard();
+                    previous.contains(*link, &previous_guard)
+                })
+            })
+            .cloned()
+            .collect();
+        for link in duplicates {
+            layer.remove(&link, &guard);
+        }
+    }
+
     fn find_in_previous_layer(
         previous_layers: LayerGroupRef,
         known_redirects: RedirectMapRef,
-        endpoint: &str,
+        endpoint: &Title,
     ) -> Result<Option<usize>, LinkCalcError> {
         let guard = known_redirects.guard();
-        let real_endpoint = known_redirects.get(endpoint, &guard).map_or(endpoint, |s| s.as_str());
+        let real_endpoint = known_redirects.get(endpoint, &guard).unwrap_or(endpoint);
         for (layer_num, layer) in previous_layers.read()?.iter().enumerate() {
             let guard = layer.guard();
             if layer.contains(real_endpoint, &guard) {
@@ -190,7 +759,7 @@ impl LinkCalculator {
     }
 
     // Create First Layer containing start point
-    fn layer_zero(start_point: String) -> LayerRef {
+    fn layer_zero(start_point: Title) -> LayerRef {
         let start: LayerRef = Arc::new(HashSet::with_capacity(1));
         let guard = start.guard();
         start.insert(start_point, &guard);
@@ -199,6 +768,17 @@ impl LinkCalculator {
     }
 }
 
+impl fmt::Debug for LinkCalculator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LinkCalculator")
+            .field("layers", &self.layers)
+            .field("known_redirects", &self.known_redirects)
+            .field("edges", &self.edges)
+            .field("failures", &self.failures)
+            .finish_non_exhaustive()
+    }
+}
+
 impl fmt::Display for LinkCalculator {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let unlocked_layers = self.layers.read().map_err(|_| fmt::Error)?;
@@ -206,32 +786,18 @@ impl fmt::Display for LinkCalculator {
         let mut layer_iter = unlocked_layers.iter().enumerate();
 
         // First, Write the article name
-        let (_0, layer_zero) = layer_iter.next().ok_or(fmt::Error)?;
+        let (_, layer_zero) = layer_iter.next().ok_or(fmt::Error)?;
         let guard = layer_zero.guard();
-        let article_name = layer_zero.iter(&guard).next().map(|a| a.as_str()).unwrap_or("");
-        match decode_url_str(article_name) {
-            Ok(decoded) => {
-                writeln!(f, "Article Name: {}", decoded)?;
-            }
-            Err(e) => {
-                error!("Failed to parse '{}'; Reason: {}", article_name, e);
-                writeln!(f, "Endpoint Name (unparseable): {}", article_name)?;
-            }
+        match layer_zero.iter(&guard).next() {
+            Some(title) => writeln!(f, "Article Name: {}", title)?,
+            None => writeln!(f, "Article Name: ")?,
         }
 
-        while let Some((i, layer)) = layer_iter.next() {
+        for (i, layer) in layer_iter {
             let guard = layer.guard();
             writeln!(f, "{}-Hop Neighbors ({}):", i, layer.len())?;
-            for endpoint in layer.iter(&guard) {
-                match decode_url_str(endpoint) {
-                    Ok(decoded) => {
-                        writeln!(f, "\t{}", decoded)?;
-                    }
-                    Err(e) => {
-                        error!("Failed to parse '{}'; Reason: {}", endpoint, e);
-                        writeln!(f, "\t{}", endpoint)?;
-                    }
-                };
+            for title in layer.iter(&guard) {
+                writeln!(f, "\t{}", title)?;
             }
         }
 
@@ -250,15 +816,26 @@ impl fmt::Display for LinkCalculator {
 pub enum LinkCalcError {
     ArticleError(ArticleError),
     ClientError(ClientError),
+    CheckpointError(CheckpointError),
     LockError,
     NotInitializedError,
     JoinError(JoinError),
+    MissingParentError(String),
+    SerializeError(serde_json::Error),
 }
 
 impl fmt::Display for LinkCalcError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        //TODO
-        write!(f, "LinkCalcError")
+        match self {
+            Self::ArticleError(e) => write!(f, "Failed to parse article: {}", e),
+            Self::ClientError(e) => write!(f, "Failed to retrieve article: {}", e),
+            Self::CheckpointError(e) => write!(f, "Failed to save checkpoint: {}", e),
+            Self::LockError => write!(f, "A lock was poisoned by a panicked task"),
+            Self::NotInitializedError => write!(f, "Calculator has no starting layer"),
+            Self::JoinError(e) => write!(f, "Link task failed: {}", e),
+            Self::MissingParentError(title) => write!(f, "No article in the previous layer links to '{}'", title),
+            Self::SerializeError(e) => write!(f, "Failed to serialize report: {}", e),
+        }
     }
 }
 
@@ -274,12 +851,24 @@ impl From<ClientError> for LinkCalcError {
     }
 }
 
+impl From<CheckpointError> for LinkCalcError {
+    fn from(e: CheckpointError) -> LinkCalcError {
+        LinkCalcError::CheckpointError(e)
+    }
+}
+
 impl From<JoinError> for LinkCalcError {
     fn from(e: JoinError) -> LinkCalcError {
         LinkCalcError::JoinError(e)
     }
 }
 
+impl From<serde_json::Error> for LinkCalcError {
+    fn from(e: serde_json::Error) -> LinkCalcError {
+        LinkCalcError::SerializeError(e)
+    }
+}
+
 impl<T> From<PoisonError<T>> for LinkCalcError {
     fn from(_: PoisonError<T>) -> LinkCalcError {
         LinkCalcError::LockError
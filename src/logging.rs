@@ -5,16 +5,17 @@ use std::fs::File;
 use log::SetLoggerError;
 use simplelog::{format_description, ColorChoice, CombinedLogger, ConfigBuilder, LevelFilter, TermLogger, TerminalMode, WriteLogger};
 
-pub fn init_logger() -> Result<(), InitLogError> {
+// Level applies to the terminal. The log file always records at least debug messages
+pub fn init_logger(level: LevelFilter) -> Result<(), InitLogError> {
     CombinedLogger::init(vec![
         TermLogger::new(
-            LevelFilter::Info,
+            level,
             ConfigBuilder::new().add_filter_allow_str("wiki_utils").build(),
             TerminalMode::Stderr,
             ColorChoice::Auto,
         ),
         WriteLogger::new(
-            LevelFilter::Debug,
+            level.max(LevelFilter::Debug),
             ConfigBuilder::new()
                 .set_time_format_custom(format_description!("[hour]:[minute]:[second].[subsecond digits:3]"))
                 .add_filter_allow_str("wiki_utils")
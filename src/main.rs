@@ -1,30 +1,52 @@
+mod cli;
+mod config;
 mod logging;
 
-use std::env;
 use std::error::Error;
-use std::fmt;
 use std::fs::File;
-use std::io::Write;
-use std::num::NonZeroUsize;
-use std::str::FromStr;
+use std::io;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use std::time::Instant;
 
 use log::info;
+use serde_json::json;
+use tokio::signal;
+use tokio::sync::mpsc;
+use tokio::sync::mpsc::UnboundedReceiver;
 
-use wiki_utils::client::AsyncClient;
+use wiki_utils::category::CategoryCrawler;
+use wiki_utils::checkpoint::Checkpoint;
+use wiki_utils::dump::DumpSource;
 use wiki_utils::links::LinkCalculator;
+use wiki_utils::path::PathFinder;
+use wiki_utils::report::DiscoveredArticle;
+use wiki_utils::source::ArticleSource;
+use wiki_utils::sqldump::{LinkGraph, SqlDumpFiles};
+use wiki_utils::title::{Title, CATEGORY_NAMESPACE};
 
+use crate::cli::{
+    ArgumentError, CategoriesArgs, Cli, Command, CrawlArgs, GlobalOptions, InspectArgs, OutputFormat, PathArgs, PrintFormat, RandomArgs,
+};
 use crate::logging::init_logger;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
-    init_logger()?;
+    let cli = Cli::parse_and_validate();
 
-    let start = Instant::now();
+    init_logger(cli.global.log_level)?;
 
-    let args = Arguments::get()?;
+    let start = Instant::now();
 
-    let result = execute_and_print(&args.starting_article, args.layers_to_calc).await;
+    let result = match &cli.command {
+        Command::Neighbors(args) => crawl_and_write(&cli.global, &args.crawl, args.format, args.output.as_deref()).await,
+        Command::Export(args) => crawl_and_write(&cli.global, &args.crawl, OutputFormat::Graph(args.format), args.output.as_deref()).await,
+        Command::Path(args) => find_path(&cli.global, args).await,
+        Command::Random(args) => print_random(&cli.global, args).await,
+        Command::Inspect(args) => inspect_article(&cli.global, args).await,
+        Command::Categories(args) => crawl_categories(&cli.global, args).await,
+    };
 
     let elapsed = start.elapsed();
     info!("Finished in {:.3?}", elapsed);
@@ -32,63 +54,273 @@ async fn main() -> Result<(), Box<dyn Error>> {
     result
 }
 
-struct Arguments {
-    starting_article: String,
-    layers_to_calc: NonZeroUsize,
-}
+async fn crawl_and_write(
+    global: &GlobalOptions,
+    args: &CrawlArgs,
+    format: OutputFormat,
+    output: Option<&Path>,
+) -> Result<(), Box<dyn Error>> {
+    let article_name = &args.article;
+    // Dumps only cover sites that capitalize the first letter
+    let dump_title = Title::from_text(article_name, true);
 
-impl Arguments {
-    fn get() -> Result<Self, ArgumentError> {
-        let mut args = env::args();
-        let _binary = args.next();
-        let starting_article = args.next().ok_or(ArgumentError::MissingArgument)?;
-        let layers_calc_arg = args.next().ok_or(ArgumentError::MissingArgument)?;
-        let layers_to_calc = NonZeroUsize::from_str(&layers_calc_arg).map_err(|_| ArgumentError::InvalidLayerCount(layers_calc_arg))?;
-        Ok(Self {
-            starting_article,
-            layers_to_calc,
-        })
+    // A dump replaces the client entirely so no request is ever made
+    let (start, title, source): (Title, String, Arc<dyn ArticleSource>) = match (&args.dump, &args.sql_dump, &args.link_graph) {
+        (Some(path), _, _) => {
+            let dump = DumpSource::open(path)?;
+            let start = dump
+                .resolve(&dump_title)
+                .ok_or_else(|| ArgumentError::NotInDump(article_name.clone()))?
+                .clone();
+            let title = start.to_string();
+            (start, title, Arc::new(dump))
+        }
+        (None, Some(directory), graph_file) => {
+            let graph = LinkGraph::import(&SqlDumpFiles::find(directory)?)?;
+            if let Some(path) = graph_file {
+                graph.save(path)?;
+            }
+            let start = graph
+                .resolve(&dump_title)
+                .ok_or_else(|| ArgumentError::NotInDump(article_name.clone()))?;
+            let title = start.to_string();
+            (start, title, Arc::new(graph))
+        }
+        (None, None, Some(path)) => {
+            let graph = LinkGraph::load(path)?;
+            let start = graph
+                .resolve(&dump_title)
+                .ok_or_else(|| ArgumentError::NotInDump(article_name.clone()))?;
+            let title = start.to_string();
+            (start, title, Arc::new(graph))
+        }
+        // The starting article is only retrieved once, as part of the first layer. A redirect is resolved up front so the
+        // title matches a checkpoint made from the same starting point
+        (None, None, None) => {
+            let client = global.build_client()?;
+            let typed = Title::from_text(article_name, client.get_site().get_capital_links());
+            let start = match client.resolve_redirects(std::slice::from_ref(&typed)).await {
+                Ok(mut resolved) => resolved.pop().unwrap_or(typed),
+                Err(e) => {
+                    info!("Failed to resolve '{}'; Reason: {}", typed, e);
+                    typed
+                }
+            };
+            let title = start.to_string();
+            (start, title, Arc::new(client))
+        }
+    };
+
+    let mut calc = match &args.checkpoint {
+        Some(path) if args.resume && path.exists() => {
+            info!("Resuming from checkpoint {}", path.display());
+            let checkpoint = Checkpoint::load(path)?;
+            if checkpoint.get_start() != Some(&start) {
+                let checkpoint_start = checkpoint.get_start().map(Title::to_string).unwrap_or_default();
+                return Err(ArgumentError::CheckpointMismatch(checkpoint_start).into());
+            }
+            LinkCalculator::from_checkpoint(checkpoint)?
+        }
+        _ => {
+            info!("Initializing LinkCalculator");
+            LinkCalculator::new(start)
+        }
+    };
+    calc.set_source(source);
+    calc.set_max_concurrency(global.concurrency);
+    // A resumed calculation keeps the category edges it was started with
+    if args.category_edges {
+        calc.set_category_edges(true);
     }
-}
+    if let Some(path) = &args.checkpoint {
+        calc.set_checkpoint_file(path.clone(), args.checkpoint_interval);
+    }
+
+    let file_name = match output {
+        Some(path) => path.to_path_buf(),
+        None => PathBuf::from(title + "." + format.extension()),
+    };
 
-#[derive(Debug)]
-enum ArgumentError {
-    MissingArgument,
-    InvalidLayerCount(String),
+    // NDJSON records are written as they are discovered rather than after all layers are calculated. Every other format
+    // only creates the file once the calculation is done, so an interrupted or failed run leaves an earlier output alone
+    let mut stream_handle = None;
+    if let OutputFormat::Ndjson = format {
+        info!("Streaming discovered articles to {}", file_name.display());
+        let writer = BufWriter::new(File::create(&file_name)?);
+        let (sender, receiver) = mpsc::unbounded_channel();
+        calc.set_discovery_sender(sender)?;
+        stream_handle = Some(tokio::spawn(stream_ndjson(receiver, writer)));
+    }
+
+    // Layer zero is not counted
+    let layers = args.layers.get().saturating_sub(calc.get_layer_count()? - 1);
+    info!("Calculating {} additonal layers of neighbors", layers);
+    let completed = tokio::select! {
+        result = calc.compute_layers_async(layers) => {
+            result?;
+            true
+        }
+        _ = signal::ctrl_c() => false,
+    };
+    if !completed {
+        match &args.checkpoint {
+            Some(path) => {
+                info!("Interrupted; Saving checkpoint to {}", path.display());
+                calc.save_checkpoint(path).await?;
+            }
+            None if stream_handle.is_none() => info!("Interrupted; Discarding results"),
+            None => info!("Interrupted; Keeping the records streamed to {}", file_name.display()),
+        }
+        // Records already streamed are kept so they match the checkpoint. The tasks holding the other senders were
+        // aborted along with the calculation
+        if let Some(handle) = stream_handle {
+            drop(calc.take_discovery_sender());
+            handle.await??.flush()?;
+        }
+        return Err(io::Error::new(io::ErrorKind::Interrupted, "Interrupted before every layer was calculated").into());
+    }
+
+    let mut writer = match stream_handle {
+        Some(handle) => {
+            drop(calc.take_discovery_sender());
+            handle.await??
+        }
+        None => BufWriter::new(File::create(&file_name)?),
+    };
+    info!("Writing calc data to {}", file_name.display());
+    match format {
+        OutputFormat::Text => writer.write_all(calc.to_string().as_bytes())?,
+        OutputFormat::Json => calc.write_json(&mut writer)?,
+        OutputFormat::Ndjson => (),
+        OutputFormat::Graph(graph_format) => calc.export_graph(graph_format, &mut writer)?,
+    };
+    writer.flush()?;
+
+    Ok(())
 }
 
-impl fmt::Display for ArgumentError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Self::MissingArgument => write!(f, "Too few arguments given"),
-            Self::InvalidLayerCount(arg) => write!(
-                f,
-                "'{}' is not a valid layer count: Must be a nonzero unsigned {}-bit integer",
-                arg,
-                usize::BITS,
-            ),
+async fn find_path(global: &GlobalOptions, args: &PathArgs) -> Result<(), Box<dyn Error>> {
+    let mut path_finder = PathFinder::with_max_hops(args.max_hops);
+    path_finder.set_source(Arc::new(global.build_client()?));
+
+    let path = path_finder.find_path(&args.source, &args.target).await?;
+
+    let mut writer = open_output(args.output.as_deref())?;
+    match (args.format, path) {
+        (PrintFormat::Text, Some(path)) => writeln!(writer, "{}", path.join(" -> "))?,
+        (PrintFormat::Text, None) => writeln!(writer, "No path within {} hops", args.max_hops)?,
+        (PrintFormat::Json, path) => {
+            serde_json::to_writer_pretty(&mut writer, &json!({ "source": args.source, "target": args.target, "path": path }))?;
+            writeln!(writer)?;
         }
+    };
+    writer.flush()?;
+
+    Ok(())
+}
+
+async fn print_random(global: &GlobalOptions, args: &RandomArgs) -> Result<(), Box<dyn Error>> {
+    let client = global.build_client()?;
+
+    let mut titles = Vec::with_capacity(args.count.get());
+    for _ in 0..args.count.get() {
+        titles.push(client.get_random_article().await?.get_article_title()?);
     }
+
+    let mut writer = open_output(args.output.as_deref())?;
+    match args.format {
+        PrintFormat::Text => {
+            for title in &titles {
+                writeln!(writer, "{}", title)?;
+            }
+        }
+        PrintFormat::Json => {
+            serde_json::to_writer_pretty(&mut writer, &titles)?;
+            writeln!(writer)?;
+        }
+    };
+    writer.flush()?;
+
+    Ok(())
 }
 
-impl Error for ArgumentError {}
+async fn inspect_article(global: &GlobalOptions, args: &InspectArgs) -> Result<(), Box<dyn Error>> {
+    let client = global.build_client()?;
 
-async fn execute_and_print(article_name: &str, layers_to_calculate: NonZeroUsize) -> Result<(), Box<dyn Error>> {
-    let client = AsyncClient::new();
+    info!("Retrieving article: {}", args.article);
+    let article = client.get_article(&args.article).await?;
+    let title = article.get_article_title()?;
+    let lead = article.get_lead_text()?;
+    let links: Vec<String> = article.get_article_link_refs()?.iter().map(Title::to_string).collect();
 
-    info!("Retrieving starting article: {}", article_name);
-    let article = client.get_article(article_name).await?;
+    let mut writer = open_output(args.output.as_deref())?;
+    match args.format {
+        PrintFormat::Text => {
+            writeln!(writer, "Title: {}", title)?;
+            writeln!(writer, "Endpoint: {}", article.get_endpoint())?;
+            writeln!(writer, "Lead: {}", lead)?;
+            writeln!(writer, "Links: {}", links.len())?;
+            if args.links {
+                for link in &links {
+                    writeln!(writer, "\t{}", link)?;
+                }
+            }
+        }
+        PrintFormat::Json => {
+            let mut value = json!({ "title": title, "endpoint": article.get_endpoint(), "lead": lead, "link_count": links.len() });
+            if args.links {
+                value["links"] = json!(links);
+            }
+            serde_json::to_writer_pretty(&mut writer, &value)?;
+            writeln!(writer)?;
+        }
+    };
+    writer.flush()?;
 
-    info!("Initializing LinkCalculator");
-    let mut calc = LinkCalculator::from_article(&article)?;
+    Ok(())
+}
 
-    let layers = layers_to_calculate.get() - 1;
-    info!("Calculating {} additonal layers of neighbors", layers);
-    calc.compute_layers_async(layers).await?;
+async fn crawl_categories(global: &GlobalOptions, args: &CategoriesArgs) -> Result<(), Box<dyn Error>> {
+    let client = global.build_client()?;
 
-    let file_name = article.get_article_title()? + ".txt";
-    info!("Writing calc data to {}", file_name);
-    File::create(file_name)?.write_all(calc.to_string().as_bytes())?;
+    // 'Philosophy of mind' is taken to mean 'Category:Philosophy of mind'
+    let capital_links = client.get_site().get_capital_links();
+    let root = match Title::from_text(&args.category, capital_links) {
+        title if title.get_namespace() == CATEGORY_NAMESPACE => title,
+        _ => Title::from_text(&format!("Category:{}", args.category), capital_links),
+    };
+
+    let mut crawler = CategoryCrawler::new(root)?;
+    crawler.set_client(Arc::new(client));
+    // Depth zero is the category itself
+    crawler.compute_layers_async(args.depth.saturating_add(1)).await?;
+
+    let mut writer = open_output(args.output.as_deref())?;
+    match args.format {
+        PrintFormat::Text => writer.write_all(crawler.to_string().as_bytes())?,
+        PrintFormat::Json => {
+            crawler.write_json(&mut writer)?;
+            writeln!(writer)?;
+        }
+    };
+    writer.flush()?;
 
     Ok(())
 }
+
+// Standard output unless a file is given
+fn open_output(path: Option<&Path>) -> io::Result<Box<dyn Write>> {
+    match path {
+        Some(path) => Ok(Box::new(BufWriter::new(File::create(path)?))),
+        None => Ok(Box::new(io::stdout().lock())),
+    }
+}
+
+// Writes one line per discovered article until every sender is dropped
+async fn stream_ndjson(mut receiver: UnboundedReceiver<DiscoveredArticle>, mut writer: BufWriter<File>) -> io::Result<BufWriter<File>> {
+    while let Some(discovered) = receiver.recv().await {
+        serde_json::to_writer(&mut writer, &discovered)?;
+        writer.write_all(b"\n")?;
+    }
+    Ok(writer)
+}
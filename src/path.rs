@@ -0,0 +1,274 @@
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
+
+use futures::future::join_all;
+use log::{debug, error, info};
+use tokio::task::JoinError;
+
+use crate::article::ArticleError;
+use crate::client::AsyncClient;
+use crate::client::ClientError;
+use crate::source::ArticleSource;
+use crate::title::Title;
+
+const DEFAULT_MAX_HOPS: usize = 6;
+
+// Title -> (neighbor one hop closer to the side's root, hops from the side's root)
+type VisitedMap = HashMap<Title, (Option<Title>, usize)>;
+
+// Finds the shortest chain of links between two articles using a bidirectional breadth-first search.
+//
+// The forward side follows the links on each article while the backward side follows 'Special:WhatLinksHere'.
+// The smaller frontier is expanded each step and the search stops as soon as the two sides meet.
+pub struct PathFinder {
+    client: Arc<dyn ArticleSource>,
+    max_hops: usize,
+}
+
+impl PathFinder {
+    pub fn new() -> Self {
+        Self::with_max_hops(DEFAULT_MAX_HOPS)
+    }
+
+    pub fn with_max_hops(max_hops: usize) -> Self {
+        PathFinder {
+            client: Arc::new(AsyncClient::new()),
+            max_hops,
+        }
+    }
+
+    // Must support get_linking_endpoints
+    pub fn set_source(&mut self, source: Arc<dyn ArticleSource>) {
+        self.client = source;
+    }
+
+    // Returns the decoded titles of every article on the path from `source` to `target` (both included), or None if
+    // no path exists within the maximum number of hops
+    pub async fn find_path(&self, source: &str, target: &str) -> Result<Option<Vec<String>>, PathError> {
+        info!("Retrieving source article: {}", source);
+        let source = self.client.get_article(source).await?.get_endpoint().clone();
+        info!("Retrieving target article: {}", target);
+        let target = self.client.get_article(target).await?.get_endpoint().clone();
+
+        if source == target {
+            return Ok(Some(vec![source.to_string()]));
+        }
+
+        let mut forward = VisitedMap::from([(source.clone(), (None, 0))]);
+        let mut backward = VisitedMap::from([(target.clone(), (None, 0))]);
+        let mut forward_frontier = vec![source];
+        let mut backward_frontier = vec![target];
+
+        for hops in 1..=self.max_hops {
+            if forward_frontier.is_empty() || backward_frontier.is_empty() {
+                info!("Search exhausted after {} hops", hops - 1);
+                return Ok(None);
+            }
+
+            let meeting_point = if forward_frontier.len() <= backward_frontier.len() {
+                info!("Expanding forward frontier of {} articles", forward_frontier.len());
+                let (discovered, next_frontier) = self.expand_forward(forward_frontier, &mut forward).await?;
+                forward_frontier = next_frontier;
+                Self::find_meeting_point(&discovered, &forward, &backward)
+            } else {
+                info!("Expanding backward frontier of {} articles", backward_frontier.len());
+                backward_frontier = self.expand_backward(backward_frontier, &mut backward).await?;
+                Self::find_meeting_point(&backward_frontier, &forward, &backward)
+            };
+
+            if let Some(meeting_point) = meeting_point {
+                info!("Frontiers met at {} after {} hops", meeting_point, hops);
+                return Ok(Some(Self::build_path(&meeting_point, &forward, &backward)));
+            }
+        }
+
+        info!("No path found within {} hops", self.max_hops);
+        Ok(None)
+    }
+
+    // Returns every title newly visited and the next forward frontier. Links are resolved to the articles they redirect
+    // to before they are visited, since the backward side only ever visits articles
+    async fn expand_forward(&self, frontier: Vec<Title>, visited: &mut VisitedMap) -> Result<(Vec<Title>, Vec<Title>), PathError> {
+        let mut handles = Vec::with_capacity(frontier.len());
+        for endpoint in frontier {
+            let client = self.client.clone();
+            handles.push(tokio::spawn(async move {
+                let result = client.get_article_links(&endpoint).await.map(|page| (page.endpoint, page.links));
+                (endpoint, result)
+            }));
+        }
+
+        let mut discovered = Vec::new();
+        // Link -> article it was first found on, in the order they were found
+        let mut new_links: Vec<(Title, Title)> = Vec::new();
+        let mut seen_links = HashSet::new();
+        for result in join_all(handles).await {
+            let (endpoint, result) = result?;
+            let (resolved, links) = match result {
+                Ok(found) => found,
+                Err(e) => {
+                    error!("Failed to retrieve article '{}'; Reason {}", endpoint, e);
+                    continue;
+                }
+            };
+            let (parent, depth) = visited
+                .get(&endpoint)
+                .cloned()
+                .ok_or_else(|| PathError::UnknownEndpoint(endpoint.to_string()))?;
+
+            // Only happens if the source could not resolve the link beforehand. The links of the redirect are the links
+            // of its target, so the target is visited in its place without being retrieved again
+            if resolved != endpoint {
+                debug!("Found redirect: {} -> {}", endpoint, resolved);
+                if visited.contains_key(&resolved) {
+                    continue;
+                }
+                visited.insert(resolved.clone(), (parent, depth));
+                discovered.push(resolved.clone());
+            }
+
+            for link in links {
+                if !visited.contains_key(&link) && seen_links.insert(link.clone()) {
+                    new_links.push((link, resolved.clone()));
+                }
+            }
+        }
+
+        let links: Vec<Title> = new_links.iter().map(|(link, _)| link.clone()).collect();
+        let canonical_links = match self.client.resolve_redirects(&links).await {
+            Ok(canonical_links) if canonical_links.len() == links.len() => canonical_links,
+            Ok(_) => links,
+            Err(e) => {
+                error!("Failed to resolve redirects of {} links; Reason {}", links.len(), e);
+                links
+            }
+        };
+
+        let mut next_frontier = Vec::new();
+        for ((_, parent), link) in new_links.into_iter().zip(canonical_links) {
+            if !visited.contains_key(&link) {
+                let depth = visited.get(&parent).map_or(0, |(_, depth)| *depth) + 1;
+                visited.insert(link.clone(), (Some(parent), depth));
+                discovered.push(link.clone());
+                next_frontier.push(link);
+            }
+        }
+
+        Ok((discovered, next_frontier))
+    }
+
+    // Returns the next backward frontier
+    async fn expand_backward(&self, frontier: Vec<Title>, visited: &mut VisitedMap) -> Result<Vec<Title>, PathError> {
+        let mut handles = Vec::with_capacity(frontier.len());
+        for endpoint in frontier {
+            let client = self.client.clone();
+            handles.push(tokio::spawn(async move {
+                let result = client.get_linking_endpoints(&endpoint).await;
+                (endpoint, result)
+            }));
+        }
+
+        let mut next_frontier = Vec::new();
+        for result in join_all(handles).await {
+            let (endpoint, result) = result?;
+            let linking_endpoints = match result {
+                Ok(found) => found,
+                Err(e) => {
+                    error!("Failed to retrieve articles linking to '{}'; Reason {}", endpoint, e);
+                    continue;
+                }
+            };
+            let depth = visited
+                .get(&endpoint)
+                .map(|(_, depth)| *depth)
+                .ok_or_else(|| PathError::UnknownEndpoint(endpoint.to_string()))?;
+
+            for linking_endpoint in linking_endpoints {
+                if !visited.contains_key(&linking_endpoint) {
+                    visited.insert(linking_endpoint.clone(), (Some(endpoint.clone()), depth + 1));
+                    next_frontier.push(linking_endpoint);
+                }
+            }
+        }
+
+        Ok(next_frontier)
+    }
+
+    // Of the newly discovered titles seen by both sides, picks the one giving the shortest total path
+    fn find_meeting_point(new_endpoints: &[Title], forward: &VisitedMap, backward: &VisitedMap) -> Option<Title> {
+        new_endpoints
+            .iter()
+            .filter_map(|endpoint| {
+                let (_, forward_depth) = forward.get(endpoint)?;
+                let (_, backward_depth) = backward.get(endpoint)?;
+                Some((forward_depth + backward_depth, endpoint))
+            })
+            .min()
+            .map(|(_, endpoint)| endpoint.clone())
+    }
+
+    fn build_path(meeting_point: &Title, forward: &VisitedMap, backward: &VisitedMap) -> Vec<String> {
+        let mut endpoints = Self::walk_to_root(meeting_point, forward);
+        endpoints.reverse();
+        endpoints.extend(Self::walk_to_root(meeting_point, backward).into_iter().skip(1));
+        endpoints.iter().map(Title::to_string).collect()
+    }
+
+    // Includes both the starting title and the root
+    fn walk_to_root(endpoint: &Title, visited: &VisitedMap) -> Vec<Title> {
+        let mut walk = vec![endpoint.clone()];
+        let mut current = endpoint;
+        while let Some((Some(next), _)) = visited.get(current) {
+            walk.push(next.clone());
+            current = next;
+        }
+        walk
+    }
+}
+
+impl Default for PathFinder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[derive(Debug)]
+pub enum PathError {
+    ArticleError(ArticleError),
+    ClientError(ClientError),
+    JoinError(JoinError),
+    UnknownEndpoint(String),
+}
+
+impl fmt::Display for PathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ArticleError(e) => write!(f, "Failed to parse article: {}", e),
+            Self::ClientError(e) => write!(f, "Failed to retrieve article: {}", e),
+            Self::JoinError(e) => write!(f, "Search task failed: {}", e),
+            Self::UnknownEndpoint(endpoint) => write!(f, "Expanded endpoint '{}' was never visited", endpoint),
+        }
+    }
+}
+
+impl From<ArticleError> for PathError {
+    fn from(e: ArticleError) -> PathError {
+        PathError::ArticleError(e)
+    }
+}
+
+impl From<ClientError> for PathError {
+    fn from(e: ClientError) -> PathError {
+        PathError::ClientError(e)
+    }
+}
+
+impl From<JoinError> for PathError {
+    fn from(e: JoinError) -> PathError {
+        PathError::JoinError(e)
+    }
+}
+
+impl Error for PathError {}
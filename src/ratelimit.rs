@@ -0,0 +1,69 @@
+use std::sync::{Mutex, PoisonError};
+
+use tokio::time;
+use tokio::time::{Duration, Instant};
+
+// One request a day. Slower rates would make the wait for a token overflow a Duration
+pub const MIN_REQUESTS_PER_SECOND: f64 = 1.0 / 86_400.0;
+
+// Token bucket limiting how many requests are started per second. Up to burst requests can start at once after a
+// quiet period, after which they are spread out at the configured rate
+#[derive(Debug)]
+pub struct RateLimiter {
+    requests_per_second: f64,
+    burst: f64,
+    bucket: Mutex<Bucket>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    // Negative when requests are queued waiting for tokens
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl RateLimiter {
+    // Rates below MIN_REQUESTS_PER_SECOND, including ones that are not positive, are raised to it
+    pub fn new(requests_per_second: f64, burst: u32) -> Self {
+        let burst = burst.max(1) as f64;
+        RateLimiter {
+            requests_per_second: requests_per_second.max(MIN_REQUESTS_PER_SECOND),
+            burst,
+            bucket: Mutex::new(Bucket {
+                tokens: burst,
+                last_refill: Instant::now(),
+            }),
+        }
+    }
+
+    pub fn get_requests_per_second(&self) -> f64 {
+        self.requests_per_second
+    }
+
+    pub fn get_burst(&self) -> u32 {
+        self.burst as u32
+    }
+
+    // Waits until a request may be started. Callers are served in the order they called
+    pub async fn acquire(&self) {
+        let wait = self.reserve();
+        if !wait.is_zero() {
+            time::sleep(wait).await;
+        }
+    }
+
+    // Takes a token, going into debt if none are left, and returns how long until the debt is paid off
+    fn reserve(&self) -> Duration {
+        let mut bucket = self.bucket.lock().unwrap_or_else(PoisonError::into_inner);
+        let now = Instant::now();
+        let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.requests_per_second).min(self.burst);
+        bucket.last_refill = now;
+
+        bucket.tokens -= 1.0;
+        match bucket.tokens < 0.0 {
+            true => Duration::try_from_secs_f64(-bucket.tokens / self.requests_per_second).unwrap_or(Duration::MAX),
+            false => Duration::ZERO,
+        }
+    }
+}
@@ -0,0 +1,61 @@
+use serde::{Deserialize, Serialize};
+
+// Full result of a LinkCalculator run. All article names are decoded titles
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LinkReport {
+    pub start: String,
+    pub layers: Vec<LayerReport>,
+    pub redirects: Vec<RedirectReport>,
+    pub failures: Vec<FailureReport>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LayerReport {
+    pub hop: usize,
+    pub articles: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RedirectReport {
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FailureReport {
+    pub article: String,
+    pub reason: String,
+}
+
+// Emitted once for every article as soon as it is added to a layer
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiscoveredArticle {
+    pub hop: usize,
+    pub title: String,
+    pub endpoint: String,
+    // Title of the article in the previous layer the article was found on
+    pub parent: Option<String>,
+}
+
+// Full result of a CategoryCrawler run. All titles are decoded
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CategoryReport {
+    pub root: String,
+    pub layers: Vec<CategoryLayerReport>,
+    pub cycles: Vec<CycleReport>,
+    pub failures: Vec<FailureReport>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CategoryLayerReport {
+    pub depth: usize,
+    pub categories: Vec<String>,
+    // Member pages of the layer's categories that were not found at a lower depth
+    pub pages: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CycleReport {
+    // Starts and ends with the same category
+    pub categories: Vec<String>,
+}
@@ -0,0 +1,131 @@
+use std::time::{Duration, SystemTime};
+
+use reqwest::header::RETRY_AFTER;
+use reqwest::{Response, StatusCode};
+
+use crate::client::ClientError;
+
+const DEFAULT_MAX_ATTEMPTS: usize = 5;
+const DEFAULT_INITIAL_BACKOFF: Duration = Duration::from_millis(1000);
+const DEFAULT_MAX_BACKOFF: Duration = Duration::from_secs(60);
+const DEFAULT_MULTIPLIER: f64 = 2.0;
+const DEFAULT_JITTER: f64 = 0.5;
+const DEFAULT_MAX_RETRY_AFTER: Duration = Duration::from_secs(300);
+
+// Decides which failed requests are attempted again and how long to wait before doing so
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    // Includes the first attempt. 1 disables retries
+    max_attempts: usize,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    // Backoff is multiplied by this after every failed attempt
+    multiplier: f64,
+    // Fraction of the backoff that is randomized. 0 waits exactly the backoff while 1 waits anywhere between 0 and it
+    jitter: f64,
+    respect_retry_after: bool,
+    // Longer Retry-After values are capped to this so a misbehaving server can't stall a crawl indefinitely
+    max_retry_after: Duration,
+}
+
+impl RetryPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Never retries
+    pub fn none() -> Self {
+        Self::default().with_max_attempts(1)
+    }
+
+    pub fn with_max_attempts(mut self, max_attempts: usize) -> Self {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    pub fn with_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max.max(initial);
+        self
+    }
+
+    pub fn with_multiplier(mut self, multiplier: f64) -> Self {
+        self.multiplier = multiplier.max(1.0);
+        self
+    }
+
+    pub fn with_jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter.clamp(0.0, 1.0);
+        self
+    }
+
+    pub fn with_retry_after(mut self, respect_retry_after: bool, max_retry_after: Duration) -> Self {
+        self.respect_retry_after = respect_retry_after;
+        self.max_retry_after = max_retry_after;
+        self
+    }
+
+    pub fn get_max_attempts(&self) -> usize {
+        self.max_attempts
+    }
+
+    // 429s, 5xx responses, timeouts and dropped connections are worth another attempt. Other 4xx responses won't change
+    pub fn is_retryable(&self, error: &ClientError) -> bool {
+        match error {
+            ClientError::StatusCodeError(code) => is_retryable_status(*code),
+            ClientError::RequestError(e) => e.is_timeout() || e.is_connect() || e.is_request() || e.is_body(),
+            _ => false,
+        }
+    }
+
+    // Exponential backoff with jitter for the given failed attempt, starting from 1. A server provided Retry-After
+    // replaces the backoff if it is longer
+    pub fn get_delay(&self, attempt: usize, retry_after: Option<Duration>) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(i32::MAX as usize) as i32;
+        let backoff = self.initial_backoff.as_secs_f64() * self.multiplier.powi(exponent);
+        let backoff = backoff.min(self.max_backoff.as_secs_f64());
+        let jittered = match self.jitter > 0.0 && backoff > 0.0 {
+            true => backoff - rand::random_range(0.0..=backoff * self.jitter),
+            false => backoff,
+        };
+        let delay = Duration::from_secs_f64(jittered);
+        match retry_after {
+            Some(retry_after) => delay.max(retry_after),
+            None => delay,
+        }
+    }
+
+    // Reads the Retry-After header of a 429 or 503 response as either a number of seconds or an http date
+    pub fn get_retry_after(&self, response: &Response) -> Option<Duration> {
+        if !self.respect_retry_after || !matches!(response.status(), StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE) {
+            return None;
+        }
+        let value = response.headers().get(RETRY_AFTER)?.to_str().ok()?.trim();
+        let retry_after = match value.parse::<u64>() {
+            Ok(seconds) => Duration::from_secs(seconds),
+            Err(_) => httpdate::parse_http_date(value)
+                .ok()?
+                .duration_since(SystemTime::now())
+                .unwrap_or_default(),
+        };
+        Some(retry_after.min(self.max_retry_after))
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            initial_backoff: DEFAULT_INITIAL_BACKOFF,
+            max_backoff: DEFAULT_MAX_BACKOFF,
+            multiplier: DEFAULT_MULTIPLIER,
+            jitter: DEFAULT_JITTER,
+            respect_retry_after: true,
+            max_retry_after: DEFAULT_MAX_RETRY_AFTER,
+        }
+    }
+}
+
+fn is_retryable_status(code: StatusCode) -> bool {
+    code == StatusCode::TOO_MANY_REQUESTS || code == StatusCode::REQUEST_TIMEOUT || code.is_server_error()
+}
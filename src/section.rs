@@ -0,0 +1,206 @@
+use once_cell::sync::Lazy;
+use scraper::{ElementRef, Selector};
+
+use crate::article::find_article_links;
+use crate::text::extract_text;
+use crate::title::Title;
+
+const HEADING_WRAPPER_CLASS: &str = "mw-heading";
+
+// Older skins put the text and anchor of a heading in this span instead of on the heading element
+static HEADLINE_SELECTOR: Lazy<Selector> = Lazy::new(|| Selector::parse(".mw-headline").unwrap());
+
+// A heading and everything up to the next heading of the same or a higher level. The lead is a level 1 section without
+// heading text or anchor that holds the content before the first heading, with the top level sections as subsections
+#[derive(Debug, Clone)]
+pub struct Section<'a> {
+    level: u8,
+    heading: String,
+    anchor: Option<String>,
+    // The 'h2'-'h6' element, or the 'div.mw-heading' wrapping it. None for the lead
+    heading_element: Option<ElementRef<'a>>,
+    // Elements between the heading and the next heading of any level
+    content: Vec<ElementRef<'a>>,
+    subsections: Vec<Section<'a>>,
+    // Prefix of the hrefs that link to other articles
+    article_path: &'a str,
+}
+
+impl<'a> Section<'a> {
+    // Builds the section tree from the children of an article body. Links are read from hrefs under the article path
+    pub fn parse(body: ElementRef<'a>, article_path: &'a str) -> Self {
+        let mut open_sections = vec![Self::lead(article_path)];
+        Self::parse_children(body, &mut open_sections, article_path);
+        Self::close_sections(&mut open_sections, 1);
+        open_sections.pop().expect("Lead is never closed")
+    }
+
+    fn lead(article_path: &'a str) -> Self {
+        Section {
+            level: 1,
+            heading: String::new(),
+            anchor: None,
+            heading_element: None,
+            content: Vec::new(),
+            subsections: Vec::new(),
+            article_path,
+        }
+    }
+
+    // Each open section is a subsection of the one before it. Parsoid output wraps sections in 'section' elements, so
+    // those are flattened to match the output of the legacy parser
+    fn parse_children(parent: ElementRef<'a>, open_sections: &mut Vec<Section<'a>>, article_path: &'a str) {
+        for child in parent.child_elements() {
+            if let Some(section) = Self::from_heading(child, article_path) {
+                Self::close_sections(open_sections, section.level);
+                open_sections.push(section);
+            } else if child.value().name() == "section" {
+                Self::parse_children(child, open_sections, article_path);
+            } else {
+                open_sections.last_mut().expect("Lead is never closed").content.push(child);
+            }
+        }
+    }
+
+    // Moves the open sections at or below the level into their parent's subsections
+    fn close_sections(open_sections: &mut Vec<Section<'a>>, level: u8) {
+        while open_sections.len() > 1 && open_sections.last().is_some_and(|section| section.level >= level) {
+            let section = open_sections.pop().expect("More than one section is open");
+            open_sections
+                .last_mut()
+                .expect("More than one section is open")
+                .subsections
+                .push(section);
+        }
+    }
+
+    // Handles both '<div class="mw-heading"><h2 id="History">History</h2>...</div>' and the older
+    // '<h2><span class="mw-headline" id="History">History</span>...</h2>'
+    fn from_heading(element: ElementRef<'a>, article_path: &'a str) -> Option<Self> {
+        let heading = if element.value().classes().any(|class| class == HEADING_WRAPPER_CLASS) {
+            element
+                .child_elements()
+                .find(|child| heading_level(child.value().name()).is_some())?
+        } else {
+            element
+        };
+        let level = heading_level(heading.value().name())?;
+
+        let headline = heading.select(&HEADLINE_SELECTOR).next();
+        let text = headline.unwrap_or(heading).text().collect::<String>();
+        let anchor = heading.value().id().or_else(|| headline.and_then(|headline| headline.value().id()));
+
+        Some(Section {
+            level,
+            heading: text.trim().to_string(),
+            anchor: anchor.map(str::to_string),
+            heading_element: Some(element),
+            content: Vec::new(),
+            subsections: Vec::new(),
+            article_path,
+        })
+    }
+
+    // 2 for '==History==' up to 6, or 1 for the lead
+    pub fn get_level(&self) -> u8 {
+        self.level
+    }
+
+    // Empty for the lead
+    pub fn get_heading(&self) -> &str {
+        &self.heading
+    }
+
+    // Id that '#fragment' links to the section use
+    pub fn get_anchor(&self) -> Option<&str> {
+        self.anchor.as_deref()
+    }
+
+    pub fn get_content(&self) -> &[ElementRef<'a>] {
+        &self.content
+    }
+
+    pub fn get_paragraphs(&self) -> Vec<ElementRef<'a>> {
+        self.content
+            .iter()
+            .filter(|element| element.value().name() == "p")
+            .copied()
+            .collect()
+    }
+
+    pub fn get_subsections(&self) -> &[Section<'a>] {
+        &self.subsections
+    }
+
+    // Html of the content and every subsection including their headings, but not this section's own heading
+    pub fn get_html(&self) -> String {
+        let mut html: String = self.content.iter().map(|element| element.html()).collect();
+        for subsection in &self.subsections {
+            if let Some(heading) = subsection.heading_element {
+                html.push_str(&heading.html());
+            }
+            html.push_str(&subsection.get_html());
+        }
+        html
+    }
+
+    // Prose of the content before the first subsection with one line per block
+    pub fn get_content_text(&self) -> String {
+        extract_text(self.content.iter().copied())
+    }
+
+    // Prose of the content and every subsection with headings on their own lines
+    pub fn get_text(&self) -> String {
+        let mut lines = Vec::new();
+        let content_text = self.get_content_text();
+        if !content_text.is_empty() {
+            lines.push(content_text);
+        }
+        for subsection in &self.subsections {
+            lines.push(subsection.heading.clone());
+            let text = subsection.get_text();
+            if !text.is_empty() {
+                lines.push(text);
+            }
+        }
+        lines.join("\n")
+    }
+
+    // Links in the content before the first subsection. Links inside subsections belong to them
+    pub fn get_link_refs(&self) -> Vec<Title> {
+        let mut titles = Vec::new();
+        for element in &self.content {
+            titles.extend(find_article_links(*element, self.article_path));
+        }
+        titles
+    }
+
+    // This section and every section below it in document order
+    pub fn flatten(&self) -> Vec<&Section<'a>> {
+        let mut sections = vec![self];
+        for subsection in &self.subsections {
+            sections.extend(subsection.flatten());
+        }
+        sections
+    }
+
+    // First section below this one whose heading or anchor matches the name. Headings are compared ignoring case
+    pub fn find(&self, name: &str) -> Option<&Section<'a>> {
+        let anchor = name.replace(' ', "_");
+        self.flatten()
+            .into_iter()
+            .skip(1)
+            .find(|section| section.heading.eq_ignore_ascii_case(name) || section.anchor.as_deref() == Some(anchor.as_str()))
+    }
+}
+
+fn heading_level(name: &str) -> Option<u8> {
+    match name {
+        "h2" => Some(2),
+        "h3" => Some(3),
+        "h4" => Some(4),
+        "h5" => Some(5),
+        "h6" => Some(6),
+        _ => None,
+    }
+}
@@ -0,0 +1,194 @@
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+use reqwest::Url;
+
+const DEFAULT_LANGUAGE: &str = "en";
+pub(crate) const DEFAULT_ARTICLE_PATH: &str = "/wiki/";
+const DEFAULT_API_PATH: &str = "/w/api.php";
+
+// Wikimedia projects that are split by language subdomain
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Project {
+    Wikipedia,
+    Wiktionary,
+    Wikibooks,
+    Wikinews,
+    Wikiquote,
+    Wikisource,
+    Wikiversity,
+    Wikivoyage,
+}
+
+impl Project {
+    pub fn domain(&self) -> &'static str {
+        match self {
+            Self::Wikipedia => "wikipedia.org",
+            Self::Wiktionary => "wiktionary.org",
+            Self::Wikibooks => "wikibooks.org",
+            Self::Wikinews => "wikinews.org",
+            Self::Wikiquote => "wikiquote.org",
+            Self::Wikisource => "wikisource.org",
+            Self::Wikiversity => "wikiversity.org",
+            Self::Wikivoyage => "wikivoyage.org",
+        }
+    }
+}
+
+impl FromStr for Project {
+    type Err = SiteError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.strip_suffix(".org").unwrap_or(s);
+        match name {
+            "wikipedia" => Ok(Self::Wikipedia),
+            "wiktionary" => Ok(Self::Wiktionary),
+            "wikibooks" => Ok(Self::Wikibooks),
+            "wikinews" => Ok(Self::Wikinews),
+            "wikiquote" => Ok(Self::Wikiquote),
+            "wikisource" => Ok(Self::Wikisource),
+            "wikiversity" => Ok(Self::Wikiversity),
+            "wikivoyage" => Ok(Self::Wikivoyage),
+            _ => Err(SiteError::UnknownProject(s.to_string())),
+        }
+    }
+}
+
+// Location of a MediaWiki install. The host may include a port
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Site {
+    scheme: String,
+    host: String,
+    article_path: String,
+    api_path: String,
+    // Whether the first letter of every title is uppercase, as with MediaWiki's $wgCapitalLinks
+    capital_links: bool,
+}
+
+impl Site {
+    pub fn new(scheme: &str, host: &str, article_path: &str, api_path: &str) -> Self {
+        let mut article_path = article_path.to_string();
+        if !article_path.ends_with('/') {
+            article_path.push('/');
+        }
+        Site {
+            scheme: scheme.to_string(),
+            host: host.to_string(),
+            article_path,
+            api_path: api_path.to_string(),
+            capital_links: true,
+        }
+    }
+
+    pub fn wikipedia(language: &str) -> Self {
+        Self::project(language, Project::Wikipedia)
+    }
+
+    pub fn project(language: &str, project: Project) -> Self {
+        let host = format!("{}.{}", language, project.domain());
+        // Wiktionary entries are case sensitive so 'apple' and 'Apple' are different pages
+        Self::new("https", &host, DEFAULT_ARTICLE_PATH, DEFAULT_API_PATH).with_capital_links(project != Project::Wiktionary)
+    }
+
+    pub fn with_api_path(mut self, api_path: &str) -> Self {
+        self.api_path = api_path.to_string();
+        self
+    }
+
+    pub fn with_capital_links(mut self, capital_links: bool) -> Self {
+        self.capital_links = capital_links;
+        self
+    }
+
+    pub fn get_scheme(&self) -> &str {
+        &self.scheme
+    }
+
+    pub fn get_host(&self) -> &str {
+        &self.host
+    }
+
+    pub fn get_article_path(&self) -> &str {
+        &self.article_path
+    }
+
+    pub fn get_api_path(&self) -> &str {
+        &self.api_path
+    }
+
+    pub fn get_capital_links(&self) -> bool {
+        self.capital_links
+    }
+
+    // Everything before the endpoint in an article url. e.g. 'https://en.wikipedia.org/wiki/'
+    pub fn base_url(&self) -> String {
+        format!("{}://{}{}", self.scheme, self.host, self.article_path)
+    }
+
+    pub fn article_url(&self, endpoint: &str) -> String {
+        self.base_url() + endpoint
+    }
+
+    pub fn api_url(&self) -> String {
+        format!("{}://{}{}", self.scheme, self.host, self.api_path)
+    }
+}
+
+impl Default for Site {
+    fn default() -> Self {
+        Self::wikipedia(DEFAULT_LANGUAGE)
+    }
+}
+
+impl fmt::Display for Site {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.base_url())
+    }
+}
+
+// Accepts a language code ('de'), a language and project ('en.wiktionary' or 'ja.wikipedia.org') or the article url
+// prefix of any other install ('https://wiki.example.com/wiki/')
+impl FromStr for Site {
+    type Err = SiteError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.contains("://") {
+            let url = Url::parse(s).map_err(|_| SiteError::InvalidUrl(s.to_string()))?;
+            let mut host = url.host_str().ok_or_else(|| SiteError::InvalidUrl(s.to_string()))?.to_string();
+            if let Some(port) = url.port() {
+                host = format!("{}:{}", host, port);
+            }
+            let article_path = match url.path() {
+                "" | "/" => DEFAULT_ARTICLE_PATH,
+                path => path,
+            };
+            return Ok(Self::new(url.scheme(), &host, article_path, DEFAULT_API_PATH));
+        }
+
+        let (language, project) = s.split_once('.').unwrap_or((s, "wikipedia"));
+        if language.is_empty() || !language.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+            return Err(SiteError::InvalidLanguage(language.to_string()));
+        }
+        Ok(Self::project(language, Project::from_str(project)?))
+    }
+}
+
+#[derive(Debug)]
+pub enum SiteError {
+    UnknownProject(String),
+    InvalidLanguage(String),
+    InvalidUrl(String),
+}
+
+impl fmt::Display for SiteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownProject(project) => write!(f, "'{}' is not a known Wikimedia project", project),
+            Self::InvalidLanguage(language) => write!(f, "'{}' is not a valid language code", language),
+            Self::InvalidUrl(url) => write!(f, "'{}' is not a valid site url", url),
+        }
+    }
+}
+
+impl Error for SiteError {}
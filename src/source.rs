@@ -0,0 +1,102 @@
+use async_trait::async_trait;
+
+use crate::article::Article;
+use crate::client::ClientError;
+use crate::title::{Title, CATEGORY_NAMESPACE};
+
+// Links found on a single article
+#[derive(Debug, Clone)]
+pub struct PageLinks {
+    pub requested: Title,
+    // Differs from requested if the requested title is a redirect
+    pub endpoint: Title,
+    pub links: Vec<Title>,
+    // Visible categories the article is in. Empty if the source has no category data
+    pub categories: Vec<Title>,
+    // Subcategories and pages listed on a category page. Empty for every other page
+    pub members: Vec<Title>,
+}
+
+// Result of retrieving the links of up to ArticleSource::batch_size endpoints
+#[derive(Debug, Default)]
+pub struct PageLinksBatch {
+    pub pages: Vec<PageLinks>,
+    // Requested titles that could not be retrieved
+    pub failures: Vec<(Title, ClientError)>,
+}
+
+// Anything that can provide articles and the links between them. LinkCalculator and PathFinder only retrieve data
+// through this trait so caches, dump readers and test fixtures can stand in for AsyncClient
+#[async_trait]
+pub trait ArticleSource: Send + Sync {
+    // Takes a title as a user would type it. Endpoints must be parsed with Title::from_endpoint first
+    async fn get_article(&self, article_name: &str) -> Result<Article, ClientError>;
+
+    async fn get_random_article(&self) -> Result<Article, ClientError>;
+
+    // Only the members on the first page of a category's listing are read, since later pages can't be requested through
+    // get_article. AsyncClient follows the listing to its last page
+    async fn get_article_links(&self, endpoint: &Title) -> Result<PageLinks, ClientError> {
+        let article = self.get_article(&endpoint.to_string()).await?;
+        page_links_from_article(endpoint, &article)
+    }
+
+    // Never given more than batch_size endpoints
+    async fn get_article_links_batch(&self, endpoints: &[Title]) -> Result<PageLinksBatch, ClientError> {
+        get_article_links_one_by_one(self, endpoints).await
+    }
+
+    // Returns the article each title redirects to in the same order, or the title itself if it is not a redirect. Sources
+    // that can't tell return the titles unchanged
+    async fn resolve_redirects(&self, titles: &[Title]) -> Result<Vec<Title>, ClientError> {
+        Ok(titles.to_vec())
+    }
+
+    // Returns the titles of articles that link to the given title
+    async fn get_linking_endpoints(&self, endpoint: &Title) -> Result<Vec<Title>, ClientError> {
+        Err(ClientError::Unsupported(format!("Finding articles linking to '{}'", endpoint)))
+    }
+
+    fn batch_size(&self) -> usize {
+        1
+    }
+}
+
+// Links, visible categories and first page of members of an article retrieved for the requested title
+pub fn page_links_from_article(requested: &Title, article: &Article) -> Result<PageLinks, ClientError> {
+    let links = article.get_article_link_refs()?;
+    let categories = article
+        .get_categories()
+        .into_iter()
+        .filter(|category| !category.hidden)
+        .map(|category| category.title)
+        .collect();
+    let mut members = Vec::new();
+    if article.get_endpoint().get_namespace() == CATEGORY_NAMESPACE {
+        members.extend(article.get_subcategories());
+        members.extend(article.get_category_pages());
+    }
+    Ok(PageLinks {
+        requested: requested.clone(),
+        endpoint: article.get_endpoint().clone(),
+        links,
+        categories,
+        members,
+    })
+}
+
+// Default for ArticleSource::get_article_links_batch, with a get_article_links call per endpoint. Sources that only batch
+// requests some of the time fall back to it
+pub async fn get_article_links_one_by_one<S>(source: &S, endpoints: &[Title]) -> Result<PageLinksBatch, ClientError>
+where
+    S: ArticleSource + ?Sized,
+{
+    let mut batch = PageLinksBatch::default();
+    for endpoint in endpoints {
+        match source.get_article_links(endpoint).await {
+            Ok(page) => batch.pages.push(page),
+            Err(e) => batch.failures.push((endpoint.clone(), e)),
+        }
+    }
+    Ok(batch)
+}
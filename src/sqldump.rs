@@ -0,0 +1,527 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+use async_trait::async_trait;
+use flate2::bufread::MultiGzDecoder;
+use log::{debug, info};
+
+use crate::article::Article;
+use crate::client::ClientError;
+use crate::source::{ArticleSource, PageLinks};
+use crate::title::{Title, MAIN_NAMESPACE};
+
+const LINK_GRAPH_BATCH_SIZE: usize = 5000;
+// Version 2 stores the titles sorted
+const LINK_GRAPH_MAGIC: &[u8; 8] = b"WULGRPH2";
+const INSERT_PREFIX: &[u8] = b"INSERT INTO ";
+const VALUES_KEYWORD: &[u8] = b" VALUES ";
+
+// Paths of the four tables needed to build a LinkGraph
+#[derive(Debug, Clone)]
+pub struct SqlDumpFiles {
+    pub page: PathBuf,
+    pub pagelinks: PathBuf,
+    pub linktarget: PathBuf,
+    pub redirect: PathBuf,
+}
+
+impl SqlDumpFiles {
+    // Finds files named like 'enwiki-latest-page.sql.gz' or 'page.sql' in the directory
+    pub fn find(directory: &Path) -> Result<Self, SqlDumpError> {
+        let mut found: HashMap<&str, PathBuf> = HashMap::new();
+        for entry in fs::read_dir(directory)? {
+            let path = entry?.path();
+            let file_name = match path.file_name().and_then(|name| name.to_str()) {
+                Some(name) => name,
+                None => continue,
+            };
+            let stem = file_name.strip_suffix(".gz").unwrap_or(file_name);
+            for table in ["page", "pagelinks", "linktarget", "redirect"] {
+                let file = format!("{}.sql", table);
+                if stem == file || stem.ends_with(&format!("-{}", file)) {
+                    found.insert(table, path.clone());
+                }
+            }
+        }
+        let mut take = |table: &'static str| found.remove(table).ok_or(SqlDumpError::MissingTable(table));
+        Ok(SqlDumpFiles {
+            page: take("page")?,
+            pagelinks: take("pagelinks")?,
+            linktarget: take("linktarget")?,
+            redirect: take("redirect")?,
+        })
+    }
+}
+
+// Article namespace link graph in compressed sparse row form. Nodes are dense indices into titles
+#[derive(Debug, Default)]
+pub struct LinkGraph {
+    // Titles as stored in the dumps, with underscores. Sorted so a title's node is found by binary search
+    titles: Vec<String>,
+    // Links of node i are targets[offsets[i]..offsets[i + 1]]
+    offsets: Vec<u64>,
+    targets: Vec<u32>,
+    redirects: HashMap<u32, u32>,
+}
+
+impl LinkGraph {
+    pub fn import(files: &SqlDumpFiles) -> Result<Self, SqlDumpError> {
+        // (page_title, page_id)
+        let mut pages: Vec<(String, u64)> = Vec::new();
+        info!("Reading pages from {}", files.page.display());
+        for_each_row(&files.page, "page", |row| {
+            // page_id, page_namespace, page_title, ...
+            if row.get(1).and_then(SqlValue::as_i64) != Some(MAIN_NAMESPACE) {
+                return Ok(());
+            }
+            pages.push((field(row, 2, SqlValue::as_str)?.to_string(), field(row, 0, SqlValue::as_u64)?));
+            Ok(())
+        })?;
+        pages.sort_unstable();
+        pages.dedup_by(|(title, _), (kept, _)| title == kept);
+        // page_id -> node
+        let page_nodes: HashMap<u64, u32> = pages.iter().enumerate().map(|(node, (_, id))| (*id, node as u32)).collect();
+        let titles: Vec<String> = pages.into_iter().map(|(title, _)| title).collect();
+        info!("Read {} articles", titles.len());
+
+        // lt_id -> node. Targets that are not existing articles are dropped like red links in rendered pages
+        let mut target_nodes: HashMap<u64, u32> = HashMap::new();
+        info!("Reading link targets from {}", files.linktarget.display());
+        for_each_row(&files.linktarget, "linktarget", |row| {
+            // lt_id, lt_namespace, lt_title
+            if row.get(1).and_then(SqlValue::as_i64) != Some(MAIN_NAMESPACE) {
+                return Ok(());
+            }
+            let title = field(row, 2, SqlValue::as_str)?;
+            if let Some(node) = find_node(&titles, title) {
+                target_nodes.insert(field(row, 0, SqlValue::as_u64)?, node);
+            }
+            Ok(())
+        })?;
+
+        let mut redirects = HashMap::new();
+        info!("Reading redirects from {}", files.redirect.display());
+        for_each_row(&files.redirect, "redirect", |row| {
+            // rd_from, rd_namespace, rd_title, rd_interwiki, rd_fragment
+            let interwiki = row.get(3).and_then(SqlValue::as_str).unwrap_or_default();
+            if row.get(1).and_then(SqlValue::as_i64) != Some(MAIN_NAMESPACE) || !interwiki.is_empty() {
+                return Ok(());
+            }
+            let from = page_nodes.get(&field(row, 0, SqlValue::as_u64)?);
+            let to = find_node(&titles, field(row, 2, SqlValue::as_str)?);
+            if let (Some(from), Some(to)) = (from, to) {
+                redirects.insert(*from, to);
+            }
+            Ok(())
+        })?;
+        info!("Read {} redirects", redirects.len());
+
+        let mut links: Vec<(u32, u32)> = Vec::new();
+        info!("Reading links from {}", files.pagelinks.display());
+        for_each_row(&files.pagelinks, "pagelinks", |row| {
+            // pl_from, pl_from_namespace, pl_target_id
+            if row.get(1).and_then(SqlValue::as_i64) != Some(MAIN_NAMESPACE) {
+                return Ok(());
+            }
+            let from = page_nodes.get(&field(row, 0, SqlValue::as_u64)?);
+            let to = target_nodes.get(&field(row, 2, SqlValue::as_u64)?);
+            if let (Some(from), Some(to)) = (from, to) {
+                links.push((*from, *to));
+            }
+            Ok(())
+        })?;
+        info!("Read {} links", links.len());
+        drop(page_nodes);
+        drop(target_nodes);
+
+        links.sort_unstable();
+        links.dedup();
+        let mut offsets = Vec::with_capacity(titles.len() + 1);
+        let mut targets = Vec::with_capacity(links.len());
+        let mut links = links.into_iter().peekable();
+        for node in 0..titles.len() as u32 {
+            offsets.push(targets.len() as u64);
+            while let Some((_, to)) = links.next_if(|(from, _)| *from == node) {
+                targets.push(to);
+            }
+        }
+        offsets.push(targets.len() as u64);
+
+        Ok(LinkGraph {
+            titles,
+            offsets,
+            targets,
+            redirects,
+        })
+    }
+
+    // Reads a graph written by LinkGraph::save, which is much faster than importing the dumps again. Every count and
+    // offset is checked against the size of the file before anything is allocated, so a truncated or corrupt file is an
+    // error rather than a panic or an allocation failure
+    pub fn load(path: &Path) -> Result<Self, SqlDumpError> {
+        info!("Loading link graph from {}", path.display());
+        let file = File::open(path)?;
+        let file_size = file.metadata()?.len();
+        let mut reader = BufReader::new(file);
+        let mut magic = [0u8; 8];
+        reader.read_exact(&mut magic)?;
+        if &magic != LINK_GRAPH_MAGIC {
+            return Err(SqlDumpError::InvalidGraphFile);
+        }
+
+        // Each node has at least a title length and an offset
+        let node_count = checked_count(read_u64(&mut reader)?, 4 + 8, file_size)?;
+        let mut titles = Vec::with_capacity(node_count);
+        for _ in 0..node_count {
+            let title_size = read_u32(&mut reader)? as u64;
+            let mut title = Vec::new();
+            if reader.by_ref().take(title_size).read_to_end(&mut title)? as u64 != title_size {
+                return Err(SqlDumpError::InvalidGraphFile);
+            }
+            titles.push(String::from_utf8(title).map_err(|_| SqlDumpError::InvalidGraphFile)?);
+        }
+        if titles.windows(2).any(|pair| pair[0] >= pair[1]) {
+            return Err(SqlDumpError::InvalidGraphFile);
+        }
+        let offsets = (0..=node_count).map(|_| read_u64(&mut reader)).collect::<io::Result<Vec<_>>>()?;
+        if offsets.first() != Some(&0) || offsets.windows(2).any(|pair| pair[0] > pair[1]) {
+            return Err(SqlDumpError::InvalidGraphFile);
+        }
+        let edge_count = checked_count(offsets.last().copied().unwrap_or_default(), 4, file_size)?;
+        let targets = (0..edge_count).map(|_| read_u32(&mut reader)).collect::<io::Result<Vec<_>>>()?;
+        let redirect_count = checked_count(read_u64(&mut reader)?, 4 + 4, file_size)?;
+        let mut redirects = HashMap::with_capacity(redirect_count);
+        for _ in 0..redirect_count {
+            redirects.insert(read_u32(&mut reader)?, read_u32(&mut reader)?);
+        }
+        if targets
+            .iter()
+            .chain(redirects.keys())
+            .chain(redirects.values())
+            .any(|node| *node as usize >= node_count)
+        {
+            return Err(SqlDumpError::InvalidGraphFile);
+        }
+
+        info!("Loaded {} articles and {} links", node_count, edge_count);
+        Ok(LinkGraph {
+            titles,
+            offsets,
+            targets,
+            redirects,
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), SqlDumpError> {
+        info!("Saving link graph to {}", path.display());
+        let mut writer = BufWriter::new(File::create(path)?);
+        writer.write_all(LINK_GRAPH_MAGIC)?;
+        writer.write_all(&(self.titles.len() as u64).to_le_bytes())?;
+        for title in &self.titles {
+            writer.write_all(&(title.len() as u32).to_le_bytes())?;
+            writer.write_all(title.as_bytes())?;
+        }
+        for offset in &self.offsets {
+            writer.write_all(&offset.to_le_bytes())?;
+        }
+        for target in &self.targets {
+            writer.write_all(&target.to_le_bytes())?;
+        }
+        writer.write_all(&(self.redirects.len() as u64).to_le_bytes())?;
+        for (from, to) in &self.redirects {
+            writer.write_all(&from.to_le_bytes())?;
+            writer.write_all(&to.to_le_bytes())?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    pub fn get_article_count(&self) -> usize {
+        self.titles.len()
+    }
+
+    pub fn get_link_count(&self) -> usize {
+        self.targets.len()
+    }
+
+    // Returns the title of the article the title refers to, following a redirect if needed
+    pub fn resolve(&self, title: &Title) -> Option<Title> {
+        self.resolve_node(title).map(|node| self.title_of(node))
+    }
+
+    fn resolve_node(&self, title: &Title) -> Option<u32> {
+        if title.get_namespace() != MAIN_NAMESPACE {
+            return None;
+        }
+        let node = find_node(&self.titles, title.get_db_key())?;
+        Some(self.redirects.get(&node).copied().unwrap_or(node))
+    }
+
+    fn title_of(&self, node: u32) -> Title {
+        Title::new(MAIN_NAMESPACE, &self.titles[node as usize])
+    }
+
+    fn links_of(&self, node: u32) -> &[u32] {
+        let start = self.offsets[node as usize] as usize;
+        let end = self.offsets[node as usize + 1] as usize;
+        &self.targets[start..end]
+    }
+}
+
+#[async_trait]
+impl ArticleSource for LinkGraph {
+    async fn get_article(&self, article_name: &str) -> Result<Article, ClientError> {
+        Err(ClientError::Unsupported(format!("Rendering '{}' from a link graph", article_name)))
+    }
+
+    async fn get_random_article(&self) -> Result<Article, ClientError> {
+        Err(ClientError::Unsupported(String::from(
+            "Rendering a random article from a link graph",
+        )))
+    }
+
+    async fn get_article_links(&self, endpoint: &Title) -> Result<PageLinks, ClientError> {
+        let node = self.resolve_node(endpoint).ok_or(ClientError::MissingArticle)?;
+        Ok(PageLinks {
+            requested: endpoint.clone(),
+            endpoint: self.title_of(node),
+            links: self.links_of(node).iter().map(|target| self.title_of(*target)).collect(),
+            categories: Vec::new(),
+            members: Vec::new(),
+        })
+    }
+
+    fn batch_size(&self) -> usize {
+        LINK_GRAPH_BATCH_SIZE
+    }
+}
+
+fn find_node(titles: &[String], title: &str) -> Option<u32> {
+    titles
+        .binary_search_by(|probe| probe.as_str().cmp(title))
+        .ok()
+        .map(|node| node as u32)
+}
+
+// A single value of a row in a mysqldump INSERT statement
+#[derive(Debug)]
+enum SqlValue<'a> {
+    Number(&'a str),
+    String(Cow<'a, str>),
+    Null,
+}
+
+impl SqlValue<'_> {
+    fn as_i64(&self) -> Option<i64> {
+        match self {
+            Self::Number(n) => n.parse().ok(),
+            _ => None,
+        }
+    }
+
+    fn as_u64(&self) -> Option<u64> {
+        match self {
+            Self::Number(n) => n.parse().ok(),
+            _ => None,
+        }
+    }
+
+    fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(s) => Some(s),
+            _ => None,
+        }
+    }
+}
+
+fn field<'a, 'b, T>(row: &'a [SqlValue<'b>], index: usize, convert: fn(&'a SqlValue<'b>) -> Option<T>) -> Result<T, SqlDumpError> {
+    row.get(index).and_then(convert).ok_or(SqlDumpError::UnexpectedRow(index))
+}
+
+// Calls handle_row for every row inserted into table. Files ending in '.gz' are decompressed while reading
+fn for_each_row<F>(path: &Path, table: &str, mut handle_row: F) -> Result<(), SqlDumpError>
+where
+    F: FnMut(&[SqlValue]) -> Result<(), SqlDumpError>,
+{
+    let file = BufReader::new(File::open(path)?);
+    let mut reader: Box<dyn BufRead> = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("gz") => Box::new(BufReader::new(MultiGzDecoder::new(file))),
+        _ => Box::new(file),
+    };
+
+    let insert = [INSERT_PREFIX, format!("`{}`", table).as_bytes(), VALUES_KEYWORD].concat();
+    let mut line = Vec::new();
+    let mut row_count: u64 = 0;
+    loop {
+        line.clear();
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            break;
+        }
+        let values = match line.strip_prefix(insert.as_slice()) {
+            Some(values) => values,
+            None => continue,
+        };
+        let mut parser = SqlParser {
+            input: values,
+            position: 0,
+        };
+        let mut row = Vec::new();
+        while parser.parse_row(&mut row)? {
+            handle_row(&row)?;
+            row.clear();
+            row_count += 1;
+        }
+    }
+    debug!("Read {} rows from {}", row_count, path.display());
+    Ok(())
+}
+
+// Parses the '(1,0,'Title'),(2,...);' part of an INSERT statement
+struct SqlParser<'a> {
+    input: &'a [u8],
+    position: usize,
+}
+
+impl<'a> SqlParser<'a> {
+    // Returns false once the end of the statement is reached
+    fn parse_row(&mut self, row: &mut Vec<SqlValue<'a>>) -> Result<bool, SqlDumpError> {
+        match self.next_byte() {
+            Some(b'(') => (),
+            Some(b';') | None => return Ok(false),
+            Some(_) => return Err(self.syntax_error()),
+        }
+        loop {
+            row.push(self.parse_value()?);
+            match self.next_byte() {
+                Some(b',') => (),
+                Some(b')') => break,
+                _ => return Err(self.syntax_error()),
+            }
+        }
+        // Rows are separated by commas and the statement ends with a semicolon
+        if self.input.get(self.position) == Some(&b',') {
+            self.position += 1;
+        }
+        Ok(true)
+    }
+
+    fn parse_value(&mut self) -> Result<SqlValue<'a>, SqlDumpError> {
+        if self.input.get(self.position) == Some(&b'\'') {
+            self.position += 1;
+            return self.parse_string();
+        }
+        let start = self.position;
+        while let Some(byte) = self.input.get(self.position) {
+            if *byte == b',' || *byte == b')' {
+                break;
+            }
+            self.position += 1;
+        }
+        let token = std::str::from_utf8(&self.input[start..self.position]).map_err(|_| self.syntax_error())?;
+        match token {
+            "NULL" => Ok(SqlValue::Null),
+            _ => Ok(SqlValue::Number(token)),
+        }
+    }
+
+    fn parse_string(&mut self) -> Result<SqlValue<'a>, SqlDumpError> {
+        let start = self.position;
+        let mut unescaped: Option<Vec<u8>> = None;
+        loop {
+            let byte = self.next_byte().ok_or_else(|| self.syntax_error())?;
+            match byte {
+                b'\'' => break,
+                b'\\' => {
+                    let escaped = self.next_byte().ok_or_else(|| self.syntax_error())?;
+                    let buffer = unescaped.get_or_insert_with(|| self.input[start..self.position - 2].to_vec());
+                    buffer.push(match escaped {
+                        b'n' => b'\n',
+                        b'r' => b'\r',
+                        b't' => b'\t',
+                        b'0' => b'\0',
+                        b'Z' => 0x1a,
+                        other => other,
+                    });
+                }
+                other => {
+                    if let Some(buffer) = unescaped.as_mut() {
+                        buffer.push(other);
+                    }
+                }
+            }
+        }
+        // Titles are binary columns but always hold utf-8
+        match unescaped {
+            Some(buffer) => Ok(SqlValue::String(Cow::Owned(String::from_utf8_lossy(&buffer).into_owned()))),
+            None => Ok(SqlValue::String(String::from_utf8_lossy(&self.input[start..self.position - 1]))),
+        }
+    }
+
+    fn next_byte(&mut self) -> Option<u8> {
+        let byte = *self.input.get(self.position)?;
+        self.position += 1;
+        Some(byte)
+    }
+
+    fn syntax_error(&self) -> SqlDumpError {
+        SqlDumpError::SyntaxError(self.position)
+    }
+}
+
+// A count read from a graph file whose items, of at least item_size bytes each, could not all fit in the file is invalid
+fn checked_count(count: u64, item_size: u64, file_size: u64) -> Result<usize, SqlDumpError> {
+    match count.checked_mul(item_size) {
+        Some(size) if size <= file_size => usize::try_from(count).map_err(|_| SqlDumpError::InvalidGraphFile),
+        _ => Err(SqlDumpError::InvalidGraphFile),
+    }
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> io::Result<u32> {
+    let mut bytes = [0u8; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> io::Result<u64> {
+    let mut bytes = [0u8; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+#[derive(Debug)]
+pub enum SqlDumpError {
+    IoError(io::Error),
+    MissingTable(&'static str),
+    // Byte offset into the INSERT statement
+    SyntaxError(usize),
+    // Index of the column that was missing or had the wrong type
+    UnexpectedRow(usize),
+    InvalidGraphFile,
+}
+
+impl fmt::Display for SqlDumpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::IoError(e) => write!(f, "Failed to read sql dump: {}", e),
+            Self::MissingTable(table) => write!(f, "No dump file found for the '{}' table", table),
+            Self::SyntaxError(position) => write!(f, "Unexpected syntax in INSERT statement at byte {}", position),
+            Self::UnexpectedRow(column) => write!(f, "Row has a missing or invalid value in column {}", column),
+            Self::InvalidGraphFile => write!(f, "File is not a valid link graph"),
+        }
+    }
+}
+
+impl From<io::Error> for SqlDumpError {
+    fn from(e: io::Error) -> SqlDumpError {
+        SqlDumpError::IoError(e)
+    }
+}
+
+impl Error for SqlDumpError {}
@@ -0,0 +1,157 @@
+use once_cell::sync::Lazy;
+use scraper::{ElementRef, Node, Selector};
+
+// Elements that are not part of an article's prose: citation markers and reference lists, pronunciations, anything
+// hidden from readers and the navigation and maintenance boxes added by templates. Tables are left out entirely since
+// their cells do not read as sentences
+const EXCLUDED_CSS: &str = "\
+    sup.reference, .mw-ref, .reflist, .references, .mw-cite-backlink, .noprint, .mw-editsection, \
+    .IPA, .rt-commentedText, .haudio, .ext-phonos, \
+    [hidden], [style*='display:none'], [style*='display: none'], .mw-empty-elt, \
+    .hatnote, .shortdescription, .navbox, .vertical-navbox, .sidebar, .ambox, .metadata, .toc, \
+    table, figure, .thumb, .gallery, style, script";
+
+// Each one starts a new line
+const BLOCK_ELEMENTS: &[&str] = &[
+    "p",
+    "div",
+    "ul",
+    "ol",
+    "li",
+    "dl",
+    "dt",
+    "dd",
+    "blockquote",
+    "pre",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "br",
+];
+
+// Left in a line where an excluded element was dropped so only the punctuation around it is tidied. A private use
+// character, so it never appears in article text
+const REMOVED_MARKER: char = '\u{E000}';
+
+// Separators left inside brackets once everything between them was dropped
+const BRACKET_SEPARATORS: &[char] = &[REMOVED_MARKER, ' ', ';', ','];
+
+// Punctuation that never has a space before it once the element before it is dropped
+const CLOSING_PUNCTUATION: &[char] = &[',', '.', ';', ':', ')'];
+
+static EXCLUDED_SELECTOR: Lazy<Selector> = Lazy::new(|| Selector::parse(EXCLUDED_CSS).unwrap());
+
+// Returns the readable text of the elements with one line per paragraph, list item or other block
+pub fn extract_text<'a>(elements: impl IntoIterator<Item = ElementRef<'a>>) -> String {
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    for element in elements {
+        collect_text(element, &mut line, &mut lines);
+        end_line(&mut line, &mut lines);
+    }
+    lines.join("\n")
+}
+
+// Whether the element is dropped by extract_text
+pub fn is_excluded(element: ElementRef<'_>) -> bool {
+    EXCLUDED_SELECTOR.matches(&element)
+}
+
+fn collect_text(element: ElementRef<'_>, line: &mut String, lines: &mut Vec<String>) {
+    if is_excluded(element) {
+        line.push(REMOVED_MARKER);
+        return;
+    }
+    let is_block = BLOCK_ELEMENTS.contains(&element.value().name());
+    if is_block {
+        end_line(line, lines);
+    }
+    for child in element.children() {
+        match child.value() {
+            Node::Text(text) => line.push_str(text),
+            Node::Element(_) => collect_text(ElementRef::wrap(child).expect("Node is an element"), line, lines),
+            _ => (),
+        }
+    }
+    if is_block {
+        end_line(line, lines);
+    }
+}
+
+fn end_line(line: &mut String, lines: &mut Vec<String>) {
+    let cleaned = clean_line(line);
+    if !cleaned.is_empty() {
+        lines.push(cleaned);
+    }
+    line.clear();
+}
+
+// Collapses whitespace and tidies the punctuation that removed elements leave behind. e.g. 'Mind ( ; listen) is'. Text
+// that was never next to a removed element, such as 'printf()', is left as it is
+fn clean_line(line: &str) -> String {
+    let collapsed = line.split_whitespace().collect::<Vec<_>>().join(" ");
+    let cleaned = remove_spaces_at_markers(&clean_brackets(&collapsed));
+    cleaned.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+// Trims the separators next to removed elements inside brackets. Brackets left with nothing in them are replaced by a
+// marker so the space before them is tidied too
+fn clean_brackets(line: &str) -> String {
+    let mut groups = vec![String::new()];
+    for c in line.chars() {
+        match c {
+            '(' => groups.push(String::new()),
+            ')' if groups.len() > 1 => {
+                let inner = groups.pop().expect("Checked by guard");
+                let outer = groups.last_mut().expect("The line is never popped");
+                if !inner.contains(REMOVED_MARKER) {
+                    outer.push('(');
+                    outer.push_str(&inner);
+                    outer.push(')');
+                    continue;
+                }
+                match inner.trim_matches(BRACKET_SEPARATORS) {
+                    "" => outer.push(REMOVED_MARKER),
+                    trimmed => {
+                        outer.push('(');
+                        outer.push_str(trimmed);
+                        outer.push(')');
+                    }
+                }
+            }
+            _ => groups.last_mut().expect("The line is never popped").push(c),
+        }
+    }
+    // Brackets that are never closed are kept as they were
+    let mut groups = groups.into_iter();
+    let mut cleaned = groups.next().expect("The line is never popped");
+    for group in groups {
+        cleaned.push('(');
+        cleaned.push_str(&group);
+    }
+    cleaned
+}
+
+// Drops the markers along with the space they leave before punctuation or at the end of the line
+fn remove_spaces_at_markers(line: &str) -> String {
+    let mut cleaned = String::with_capacity(line.len());
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != ' ' && c != REMOVED_MARKER {
+            cleaned.push(c);
+            continue;
+        }
+        let mut run = String::from(c);
+        while let Some(next) = chars.next_if(|next| *next == ' ' || *next == REMOVED_MARKER) {
+            run.push(next);
+        }
+        if !run.contains(REMOVED_MARKER) {
+            cleaned.push_str(&run);
+        } else if run.contains(' ') && !chars.peek().is_none_or(|next| CLOSING_PUNCTUATION.contains(next)) && !cleaned.ends_with('(') {
+            cleaned.push(' ');
+        }
+    }
+    cleaned
+}
@@ -0,0 +1,133 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::url::{decode_url_str, encode_url_str, normalize_title, DecodeError};
+
+pub const MAIN_NAMESPACE: i64 = 0;
+pub const CATEGORY_NAMESPACE: i64 = 14;
+
+// Canonical English names of the namespaces every MediaWiki install has. The project namespace (4 and 5) is named
+// after the site so titles in it are kept in the main namespace. Localized names are not recognized either
+const NAMESPACES: &[(i64, &str)] = &[
+    (-2, "Media"),
+    (-1, "Special"),
+    (1, "Talk"),
+    (2, "User"),
+    (3, "User talk"),
+    (6, "File"),
+    (7, "File talk"),
+    (8, "MediaWiki"),
+    (9, "MediaWiki talk"),
+    (10, "Template"),
+    (11, "Template talk"),
+    (12, "Help"),
+    (13, "Help talk"),
+    (14, "Category"),
+    (15, "Category talk"),
+    (100, "Portal"),
+    (101, "Portal talk"),
+    (118, "Draft"),
+    (119, "Draft talk"),
+];
+
+// Older names that MediaWiki still resolves
+const NAMESPACE_ALIASES: &[(i64, &str)] = &[(6, "Image"), (7, "Image talk")];
+
+// A page title in its canonical form. Two titles are equal if and only if they name the same page, however they were
+// written or encoded
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(into = "String", try_from = "String")]
+pub struct Title {
+    namespace: i64,
+    // Title without the namespace prefix with underscores instead of spaces. e.g. 'Direct_and_indirect_realism'
+    db_key: String,
+}
+
+impl Title {
+    // For titles that are already canonical, such as those read from a database dump
+    pub fn new(namespace: i64, db_key: &str) -> Self {
+        Title {
+            namespace,
+            db_key: db_key.to_string(),
+        }
+    }
+
+    // Parses a title as a user would type it. The first letter is capitalized if the site capitalizes links
+    pub fn from_text(text: &str, capital_links: bool) -> Self {
+        let normalized = normalize_title(text, false);
+        let normalized = normalized.strip_prefix(':').map_or(normalized.as_str(), str::trim_start);
+
+        let (namespace, name) = match normalized.split_once(':') {
+            Some((prefix, name)) => match namespace_id(prefix.trim_end()) {
+                Some(namespace) => (namespace, name.trim_start()),
+                None => (MAIN_NAMESPACE, normalized),
+            },
+            None => (MAIN_NAMESPACE, normalized),
+        };
+        Title {
+            namespace,
+            db_key: normalize_title(name, capital_links).replace(' ', "_"),
+        }
+    }
+
+    // Parses the part of an article url after the article path. Links are already canonical so nothing is capitalized
+    pub fn from_endpoint(endpoint: &str) -> Result<Self, DecodeError> {
+        Ok(Self::from_text(&decode_url_str(endpoint)?, false))
+    }
+
+    pub fn get_namespace(&self) -> i64 {
+        self.namespace
+    }
+
+    pub fn get_db_key(&self) -> &str {
+        &self.db_key
+    }
+
+    // Title with namespace prefix and spaces, as shown at the top of the page. e.g. 'Help:Editing pages'
+    pub fn get_display_text(&self) -> String {
+        let text = self.db_key.replace('_', " ");
+        match namespace_name(self.namespace) {
+            Some(prefix) => format!("{}:{}", prefix, text),
+            None => text,
+        }
+    }
+
+    // Url encoded form that follows a site's article path. e.g. 'Help:Editing_pages'
+    pub fn get_endpoint(&self) -> String {
+        encode_url_str(&self.get_display_text())
+    }
+}
+
+impl fmt::Display for Title {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.get_display_text())
+    }
+}
+
+// Serialized as the endpoint so files written before titles were typed can still be read
+impl From<Title> for String {
+    fn from(title: Title) -> String {
+        title.get_endpoint()
+    }
+}
+
+impl TryFrom<String> for Title {
+    type Error = DecodeError;
+
+    fn try_from(endpoint: String) -> Result<Self, Self::Error> {
+        Self::from_endpoint(&endpoint)
+    }
+}
+
+fn namespace_id(prefix: &str) -> Option<i64> {
+    NAMESPACES
+        .iter()
+        .chain(NAMESPACE_ALIASES)
+        .find(|(_, name)| name.eq_ignore_ascii_case(prefix))
+        .map(|(namespace, _)| *namespace)
+}
+
+fn namespace_name(namespace: i64) -> Option<&'static str> {
+    NAMESPACES.iter().find(|(id, _)| *id == namespace).map(|(_, name)| *name)
+}
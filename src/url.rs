@@ -11,6 +11,42 @@ pub fn decode_url_str(url: &str) -> Result<String, DecodeError> {
     decoder.finalize()
 }
 
+// Inverse of decode_url_str. Follows MediaWiki's wfUrlencode so the result matches the hrefs found in article html
+pub fn encode_url_str(title: &str) -> String {
+    const KEPT_CHARS: &[u8] = b"-_.~;@$!*(),/:";
+
+    let mut encoded = String::with_capacity(title.len());
+    for byte in title.bytes() {
+        match byte {
+            b' ' => encoded.push('_'),
+            b if b.is_ascii_alphanumeric() || KEPT_CHARS.contains(&b) => encoded.push(b as char),
+            b => encoded.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    encoded
+}
+
+// Puts a title as a user would type it into the form MediaWiki stores it under: underscores become spaces, leading
+// and trailing whitespace is removed, runs of whitespace collapse and the first letter is capitalized if the site
+// capitalizes links
+pub fn normalize_title(title: &str, capital_links: bool) -> String {
+    let collapsed = title
+        .split(|c: char| c == '_' || c.is_whitespace())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+    let mut chars = collapsed.chars();
+    match (capital_links, chars.next()) {
+        (true, Some(first)) => first.to_uppercase().chain(chars).collect(),
+        _ => collapsed,
+    }
+}
+
+// Normalizes and encodes a title so it can be appended to a site's article path
+pub fn encode_title(title: &str, capital_links: bool) -> String {
+    encode_url_str(&normalize_title(title, capital_links))
+}
+
 struct Decoder {
     output_buffer: String,
     parse_buffer: String,
@@ -37,7 +73,7 @@ impl Decoder {
             }
             DecoderState::Parsing => {
                 self.parse_buffer.push(c);
-                if self.parse_buffer.len() % 2 == 0 {
+                if self.parse_buffer.len().is_multiple_of(2) {
                     self.state = DecoderState::ParseReady;
                 }
             }
@@ -48,8 +84,8 @@ impl Decoder {
                     let parsed = Self::hex_string_to_unicode(&self.parse_buffer)?;
                     self.output_buffer += &parsed;
                     self.parse_buffer.clear();
-                    self.output_buffer.push(c);
                     self.state = DecoderState::Reading;
+                    self.process_char(c)?;
                 }
             }
         }
@@ -59,7 +95,7 @@ impl Decoder {
     fn hex_string_to_unicode(hex_code: &str) -> Result<String, DecodeError> {
         const HEX_CHARS_PER_BYTE: usize = 2;
 
-        if hex_code.len() % HEX_CHARS_PER_BYTE  != 0 {
+        if !hex_code.len().is_multiple_of(HEX_CHARS_PER_BYTE) {
             return Err(DecodeError::OddLengthHexString);
         }
 
@@ -0,0 +1,58 @@
+use crate::url::normalize_title;
+
+const LINK_OPEN: &str = "[[";
+const LINK_CLOSE: &str = "]]";
+const REDIRECT_KEYWORD: &str = "#redirect";
+
+// Returns the titles of every [[wikilink]] in the text, skipping the same namespaced links that
+// Article::get_article_link_refs skips. Titles are normalized the way MediaWiki resolves link targets. Dumps don't say
+// whether their wiki capitalizes links so the first letter is always capitalized
+pub fn extract_wikilinks(text: &str) -> Vec<String> {
+    let mut titles = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find(LINK_OPEN) {
+        rest = &rest[start + LINK_OPEN.len()..];
+        let end = match rest.find(LINK_CLOSE) {
+            Some(end) => end,
+            None => break,
+        };
+        let inner = &rest[..end];
+        // A nested '[[' means this opening bracket belongs to something like an image caption
+        if inner.contains(LINK_OPEN) {
+            continue;
+        }
+        if let Some(title) = parse_link_target(inner) {
+            titles.push(title);
+        }
+        rest = &rest[end + LINK_CLOSE.len()..];
+    }
+    titles
+}
+
+// Returns the target title of a '#REDIRECT [[Target]]' page
+pub fn parse_redirect(text: &str) -> Option<String> {
+    let trimmed = text.trim_start();
+    let keyword = trimmed.get(..REDIRECT_KEYWORD.len())?;
+    if !keyword.eq_ignore_ascii_case(REDIRECT_KEYWORD) {
+        return None;
+    }
+    let rest = trimmed[REDIRECT_KEYWORD.len()..].trim_start();
+    let rest = rest.strip_prefix(':').unwrap_or(rest).trim_start();
+    let inner = rest.strip_prefix(LINK_OPEN)?;
+    let end = inner.find(LINK_CLOSE)?;
+    let target = inner[..end].split('|').next()?;
+    let target = target.split('#').next()?;
+    let title = normalize_title(target, true);
+    (!title.is_empty()).then_some(title)
+}
+
+fn parse_link_target(inner: &str) -> Option<String> {
+    let target = inner.split('|').next()?;
+    if target.contains(['\n', '{', '}', '<', '>', '[', ']']) || target.contains(':') {
+        return None;
+    }
+    // Links to a section of the same page have no title
+    let target = target.split('#').next()?;
+    let title = normalize_title(target, true);
+    (!title.is_empty()).then_some(title)
+}
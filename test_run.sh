@@ -1,2 +1,2 @@
 #!/bin/sh
-cargo run --release "Direct and indirect realism" 4
+cargo run --release neighbors "Direct and indirect realism" 4
@@ -0,0 +1,317 @@
+use scraper::Html;
+
+use wiki_utils::article::{Article, CategoryLink, InfoboxField};
+use wiki_utils::title::Title;
+
+// Heading markup of MediaWiki 1.43 and later
+const CURRENT_MARKUP: &str = r#"<html><body>
+<h1 id="firstHeading"><span class="mw-page-title-main">Mind</span></h1>
+<div id="mw-content-text"><div class="mw-parser-output">
+<p>The <a href="/wiki/Mind">mind</a> thinks with the <a href="/wiki/Brain">brain</a>.</p>
+<p>Second lead paragraph.</p>
+<div class="mw-heading mw-heading2"><h2 id="History">History</h2><span class="mw-editsection">[edit]</span></div>
+<p>Studied by <a href="/wiki/Aristotle">Aristotle</a>.</p>
+<div class="mw-heading mw-heading3"><h3 id="Early_modern">Early modern</h3></div>
+<p>See <a href="/wiki/Ren%C3%A9_Descartes#Dualism">Descartes</a>.</p>
+<ul><li><a href="/wiki/Dualism">Dualism</a></li></ul>
+<div class="mw-heading mw-heading2"><h2 id="See_also">See also</h2></div>
+<ul><li><a href="/wiki/Consciousness">Consciousness</a></li></ul>
+</div></div>
+</body></html>"#;
+
+// Heading markup of older MediaWiki versions
+const LEGACY_MARKUP: &str = r#"<html><body>
+<h1 id="firstHeading"><span class="mw-page-title-main">Mind</span></h1>
+<div id="mw-content-text"><div class="mw-parser-output">
+<p>Lead.</p>
+<h2><span class="mw-headline" id="History">History</span><span class="mw-editsection">[edit]</span></h2>
+<p>Old.</p>
+<h4><span class="mw-headline" id="Skipped_level">Skipped level</span></h4>
+<p>Deep.</p>
+<h3><span class="mw-headline" id="Middle">Middle</span></h3>
+<p>Between.</p>
+</div></div>
+</body></html>"#;
+
+// Lead and section with the clutter of a typical Wikipedia article
+const CLUTTERED_MARKUP: &str = r##"<html><body>
+<h1 id="firstHeading"><span class="mw-page-title-main">Mind</span></h1>
+<div id="mw-content-text"><div class="mw-parser-output">
+<div class="shortdescription nomobile noexcerpt noprint searchaux" style="display:none">Thinking faculty</div>
+<div role="note" class="hatnote navigation-not-searchable">For other uses, see <a href="/wiki/Mind_(disambiguation)">Mind (disambiguation)</a>.</div>
+<table class="infobox"><tr><th>Mind</th></tr></table>
+<p class="mw-empty-elt"></p>
+<p>The <b>mind</b> (<span class="rt-commentedText nowrap"><span class="IPA">/maɪnd/</span></span>; <span class="ext-phonos">listen</span>) is
+the set of <a href="/wiki/Faculty">faculties</a> responsible for <a href="/wiki/Thought">thought</a>.<sup id="cite_ref-1" class="reference"><a href="#cite_note-1">[1]</a></sup>
+It is studied by many fields<sup class="noprint Inline-Template Template-Fact">[<i>citation needed</i>]</sup>, including:</p>
+<ul><li>Psychology</li><li>Philosophy<span style="display: none">hidden</span></li></ul>
+<div class="mw-heading mw-heading2"><h2 id="References">References</h2><span class="mw-editsection">[edit]</span></div>
+<div class="reflist"><ol class="references"><li id="cite_note-1">A source</li></ol></div>
+<div class="navbox"><a href="/wiki/Philosophy">Philosophy</a></div>
+</div></div>
+</body></html>"##;
+
+// A person infobox with a subbox followed by a second infobox further down the page
+const INFOBOX_MARKUP: &str = r##"<html><body>
+<h1 id="firstHeading"><span class="mw-page-title-main">René Descartes</span></h1>
+<div id="mw-content-text"><div class="mw-parser-output">
+<table class="infobox biography vcard"><tbody>
+<tr><th colspan="2" class="infobox-above"><div class="fn">René Descartes</div></th></tr>
+<tr><td colspan="2" class="infobox-image"><span typeof="mw:File"><a href="/wiki/File:Frans_Hals_-_Portret_van_Ren%C3%A9_Descartes.jpg" class="mw-file-description"><img src="//upload.wikimedia.org/descartes.jpg" width="220"></a></span><div class="infobox-caption">Portrait after <a href="/wiki/Frans_Hals">Frans Hals</a>, c. 1649</div></td></tr>
+<tr><th scope="row" class="infobox-label">Born</th><td class="infobox-data">31 March 1596<br><a href="/wiki/Descartes,_Indre-et-Loire">La Haye en Touraine</a>, <a href="/wiki/Kingdom_of_France">France</a><sup class="reference"><a href="#cite_note-2">[2]</a></sup></td></tr>
+<tr><td colspan="2" class="infobox-full-data"><table class="infobox-subbox"><tbody>
+<tr><th scope="row" class="infobox-label">Era</th><td class="infobox-data"><a href="/wiki/17th-century_philosophy">17th-century philosophy</a></td></tr>
+</tbody></table></td></tr>
+<tr><th colspan="2" class="infobox-header">Philosophical work</th></tr>
+<tr><th scope="row" class="infobox-label">Notable ideas</th><td class="infobox-data"><div class="plainlist"><ul><li><a href="/wiki/Cogito,_ergo_sum">Cogito, ergo sum</a></li><li><a href="/wiki/Mind%E2%80%93body_dualism">Mind–body dualism</a></li></ul></div></td></tr>
+</tbody></table>
+<p>René Descartes was a philosopher.</p>
+<table class="infobox"><caption class="infobox-title">Signature</caption><tbody>
+<tr><th>Language</th><td>French</td></tr>
+</tbody></table>
+</div></div>
+</body></html>"##;
+
+// Category page that is itself in a visible, a missing and a hidden category
+const CATEGORY_MARKUP: &str = r##"<html><body>
+<h1 id="firstHeading"><span class="mw-page-title-main">Category:Philosophy of mind</span></h1>
+<div id="mw-content-text"><div class="mw-parser-output"><p>Articles about the <a href="/wiki/Mind">mind</a>.</p></div>
+<div class="mw-category-generated">
+<div id="mw-subcategories"><h2>Subcategories</h2><div class="mw-category"><ul>
+<li><div class="CategoryTreeItem"><span class="CategoryTreeBullet"></span> <a href="/wiki/Category:Consciousness">Consciousness</a></div></li>
+</ul></div></div>
+<div id="mw-pages"><h2>Pages in category "Philosophy of mind"</h2>
+(previous page) (<a href="/w/index.php?title=Category:Philosophy_of_mind&amp;pagefrom=Qualia#mw-pages">next page</a>)
+<div class="mw-category"><ul><li><a href="/wiki/Mind">Mind</a></li><li><a href="/wiki/Qualia">Qualia</a></li></ul></div></div>
+</div></div>
+<div id="catlinks" class="catlinks"><div id="mw-normal-catlinks" class="mw-normal-catlinks"><a href="/wiki/Help:Category">Categories</a>: <ul>
+<li><a href="/wiki/Category:Philosophy_by_topic">Philosophy by topic</a></li>
+<li><a href="/w/index.php?title=Category:Unwritten_topics&amp;action=edit&amp;redlink=1" class="new">Unwritten topics</a></li>
+</ul></div><div id="mw-hidden-catlinks" class="mw-hidden-catlinks mw-hidden-cats-hidden">Hidden categories: <ul>
+<li><a href="/wiki/Category:Commons_category_link_is_on_Wikidata">Commons category link is on Wikidata</a></li>
+</ul></div></div>
+</body></html>"##;
+
+fn article(html: &str) -> Article {
+    Article::new(Title::from_text("Mind", true), Html::parse_document(html))
+}
+
+fn titles(texts: &[&str]) -> Vec<Title> {
+    texts.iter().map(|text| Title::from_text(text, true)).collect()
+}
+
+#[test]
+fn sections_are_nested_by_level() {
+    let article = article(CURRENT_MARKUP);
+    let lead = article.get_sections().unwrap();
+
+    assert_eq!(lead.get_level(), 1);
+    assert_eq!(lead.get_paragraphs().len(), 2);
+    let headings: Vec<(u8, &str, Option<&str>)> = lead
+        .flatten()
+        .iter()
+        .map(|section| (section.get_level(), section.get_heading(), section.get_anchor()))
+        .collect();
+    assert_eq!(
+        headings,
+        vec![
+            (1, "", None),
+            (2, "History", Some("History")),
+            (3, "Early modern", Some("Early_modern")),
+            (2, "See also", Some("See_also"))
+        ]
+    );
+    assert_eq!(lead.get_subsections().len(), 2);
+    assert_eq!(lead.get_subsections()[0].get_subsections()[0].get_heading(), "Early modern");
+}
+
+#[test]
+fn legacy_headings_are_parsed() {
+    let article = article(LEGACY_MARKUP);
+    let lead = article.get_sections().unwrap();
+
+    let history = &lead.get_subsections()[0];
+    assert_eq!(history.get_heading(), "History");
+    assert_eq!(history.get_anchor(), Some("History"));
+    // A level 3 heading after a level 4 one is a sibling of it rather than its child
+    let children: Vec<&str> = history.get_subsections().iter().map(|section| section.get_heading()).collect();
+    assert_eq!(children, vec!["Skipped level", "Middle"]);
+    assert_eq!(article.get_lead_string().unwrap(), "<p>Lead.</p>");
+}
+
+#[test]
+fn links_belong_to_their_section() {
+    let article = article(CURRENT_MARKUP);
+    let lead = article.get_sections().unwrap();
+
+    let links: Vec<(&str, Vec<Title>)> = lead
+        .flatten()
+        .into_iter()
+        .map(|section| (section.get_heading(), section.get_link_refs()))
+        .collect();
+    assert_eq!(
+        links,
+        vec![
+            ("", titles(&["Mind", "Brain"])),
+            ("History", titles(&["Aristotle"])),
+            ("Early modern", titles(&["René Descartes", "Dualism"])),
+            ("See also", titles(&["Consciousness"])),
+        ]
+    );
+    let all_links: Vec<Title> = links.into_iter().flat_map(|(_, links)| links).collect();
+    assert_eq!(all_links, article.get_article_link_refs().unwrap());
+}
+
+#[test]
+fn sections_are_found_by_heading_or_anchor() {
+    let article = article(CURRENT_MARKUP);
+
+    let section = article.get_section("see also").unwrap().unwrap();
+    assert_eq!(section.get_text(), "Consciousness");
+    assert_eq!(article.get_section("Early_modern").unwrap().unwrap().get_heading(), "Early modern");
+    assert!(article.get_section("References").unwrap().is_none());
+}
+
+#[test]
+fn section_text_and_html_include_subsections() {
+    let article = article(CURRENT_MARKUP);
+    let history = article.get_section("History").unwrap().unwrap();
+
+    assert_eq!(history.get_text(), "Studied by Aristotle.\nEarly modern\nSee Descartes.\nDualism");
+    let html = history.get_html();
+    assert!(html.starts_with("<p>Studied by"));
+    assert!(html.contains("<h3 id=\"Early_modern\">Early modern</h3>"));
+    assert!(!html.contains("Consciousness"));
+}
+
+#[test]
+fn lead_text_is_clean_prose() {
+    let article = article(CLUTTERED_MARKUP);
+
+    assert_eq!(
+        article.get_lead_text().unwrap(),
+        "The mind is the set of faculties responsible for thought. It is studied by many fields, including:\nPsychology\nPhilosophy"
+    );
+}
+
+#[test]
+fn article_text_has_headings_on_their_own_lines() {
+    let article = article(CURRENT_MARKUP);
+
+    let text = article.get_text().unwrap();
+    assert_eq!(text.lines().next(), Some("The mind thinks with the brain."));
+    assert!(text.contains("\nHistory\nStudied by Aristotle.\nEarly modern\n"));
+    assert_eq!(
+        article.get_section_text("Early modern").unwrap(),
+        Some(String::from("See Descartes.\nDualism"))
+    );
+    assert_eq!(article.get_section_text("Reception").unwrap(), None);
+}
+
+#[test]
+fn punctuation_is_only_tidied_where_elements_were_removed() {
+    let article = article(
+        r##"<html><body><div id="mw-content-text"><div class="mw-parser-output">
+<p>Call <code>printf()</code> or f() from C or .NET code ( see below )<sup class="reference"><a href="#cite_note-2">[2]</a></sup>.
+Hosted in a process (<span class="IPA">/ˈprɒsɛs/</span>, or task)<sup class="reference">[3]</sup> , usually.</p>
+</div></div></body></html>"##,
+    );
+
+    assert_eq!(
+        article.get_lead_text().unwrap(),
+        "Call printf() or f() from C or .NET code ( see below ). Hosted in a process (or task), usually."
+    );
+}
+
+#[test]
+fn reference_sections_have_no_text() {
+    let article = article(CLUTTERED_MARKUP);
+
+    assert_eq!(article.get_section_text("References").unwrap(), Some(String::new()));
+}
+
+#[test]
+fn infoboxes_are_parsed_in_order() {
+    let article = article(INFOBOX_MARKUP);
+    let infoboxes = article.get_infoboxes().unwrap();
+
+    assert_eq!(infoboxes.len(), 2);
+    assert_eq!(infoboxes[0].title.as_deref(), Some("René Descartes"));
+    assert_eq!(infoboxes[1].title.as_deref(), Some("Signature"));
+    assert_eq!(infoboxes[1].image, None);
+    assert_eq!(
+        infoboxes[1].fields,
+        vec![InfoboxField {
+            label: String::from("Language"),
+            value: String::from("French"),
+            links: Vec::new()
+        }]
+    );
+}
+
+#[test]
+fn infobox_fields_have_text_and_links() {
+    let article = article(INFOBOX_MARKUP);
+    let infobox = article.get_infoboxes().unwrap().remove(0);
+
+    let labels: Vec<&str> = infobox.fields.iter().map(|field| field.label.as_str()).collect();
+    assert_eq!(labels, vec!["Born", "Era", "Notable ideas"]);
+
+    let born = &infobox.fields[0];
+    assert_eq!(born.value, "31 March 1596\nLa Haye en Touraine, France");
+    assert_eq!(born.links, titles(&["Descartes, Indre-et-Loire", "Kingdom of France"]));
+    assert_eq!(infobox.fields[2].value, "Cogito, ergo sum\nMind–body dualism");
+    assert_eq!(infobox.fields[2].links, titles(&["Cogito, ergo sum", "Mind–body dualism"]));
+}
+
+#[test]
+fn infobox_image_is_parsed() {
+    let article = article(INFOBOX_MARKUP);
+    let image = article.get_infoboxes().unwrap().remove(0).image.unwrap();
+
+    assert_eq!(
+        image.file,
+        Some(Title::from_text("File:Frans Hals - Portret van René Descartes.jpg", true))
+    );
+    assert_eq!(image.source, "//upload.wikimedia.org/descartes.jpg");
+    assert_eq!(image.caption.as_deref(), Some("Portrait after Frans Hals, c. 1649"));
+}
+
+#[test]
+fn categories_are_read_from_both_blocks() {
+    let category_page = article(CATEGORY_MARKUP);
+
+    let category = |text: &str, hidden: bool| CategoryLink {
+        title: Title::from_text(text, true),
+        hidden,
+    };
+    assert_eq!(
+        category_page.get_categories(),
+        vec![
+            category("Category:Philosophy by topic", false),
+            category("Category:Unwritten topics", false),
+            category("Category:Commons category link is on Wikidata", true),
+        ]
+    );
+    assert!(article(CURRENT_MARKUP).get_categories().is_empty());
+}
+
+#[test]
+fn category_members_are_split() {
+    let article = article(CATEGORY_MARKUP);
+
+    assert_eq!(article.get_subcategories(), titles(&["Category:Consciousness"]));
+    assert_eq!(article.get_category_pages(), titles(&["Mind", "Qualia"]));
+    assert_eq!(article.get_article_link_refs().unwrap(), titles(&["Mind"]));
+}
+
+#[test]
+fn next_listing_pages_are_found() {
+    let article = article(CATEGORY_MARKUP);
+
+    assert_eq!(
+        article.get_next_category_pages_ref().as_deref(),
+        Some("/w/index.php?title=Category:Philosophy_of_mind&pagefrom=Qualia#mw-pages")
+    );
+    assert_eq!(article.get_next_subcategories_ref(), None);
+}
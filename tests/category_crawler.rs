@@ -0,0 +1,138 @@
+mod support;
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use wiki_utils::category::CategoryCrawler;
+use wiki_utils::client::AsyncClient;
+use wiki_utils::report::CategoryReport;
+use wiki_utils::retry::RetryPolicy;
+use wiki_utils::title::Title;
+
+use support::{Fault, MockServer, MockWiki};
+
+fn crawler(server: &MockServer, root: &str) -> CategoryCrawler {
+    let client = AsyncClient::builder()
+        .with_site(server.site())
+        .with_retry_policy(
+            RetryPolicy::new()
+                .with_max_attempts(2)
+                .with_backoff(Duration::from_millis(10), Duration::from_millis(20)),
+        )
+        .build()
+        .unwrap();
+    let mut crawler = CategoryCrawler::new(Title::from_text(root, true)).unwrap();
+    crawler.set_client(Arc::new(client));
+    crawler
+}
+
+fn layer_categories(report: &CategoryReport) -> Vec<Vec<&str>> {
+    report
+        .layers
+        .iter()
+        .map(|layer| layer.categories.iter().map(String::as_str).collect())
+        .collect()
+}
+
+fn layer_pages(report: &CategoryReport) -> Vec<Vec<&str>> {
+    report
+        .layers
+        .iter()
+        .map(|layer| layer.pages.iter().map(String::as_str).collect())
+        .collect()
+}
+
+#[tokio::test]
+async fn every_page_of_a_listing_is_followed() {
+    let wiki = MockWiki::new()
+        .listing_size(2)
+        .categories("Qualia", &["Category:Philosophy of mind"])
+        .categories("Consciousness", &["Category:Philosophy of mind"])
+        .categories("Intentionality", &["Category:Philosophy of mind"])
+        .categories("Mental state", &["Category:Philosophy of mind", "Category:Mental content"])
+        .categories("Category:Mental content", &["Category:Philosophy of mind"])
+        .categories("Category:Consciousness studies", &["Category:Philosophy of mind"])
+        .categories("Category:Qualia", &["Category:Philosophy of mind"])
+        .categories("Inverted spectrum", &["Category:Qualia"])
+        .categories("Category:Color", &["Category:Qualia"])
+        .categories("Hue", &["Category:Color"]);
+    let server = MockServer::start(wiki).await;
+
+    let mut crawler = crawler(&server, "Category:Philosophy of mind");
+    crawler.compute_layers_async(2).await.unwrap();
+    let report = crawler.get_report();
+
+    assert_eq!(
+        layer_categories(&report),
+        vec![
+            vec!["Category:Philosophy of mind"],
+            vec!["Category:Consciousness studies", "Category:Mental content", "Category:Qualia"],
+        ]
+    );
+    assert_eq!(
+        layer_pages(&report),
+        vec![
+            vec!["Consciousness", "Intentionality", "Mental state", "Qualia"],
+            vec!["Inverted spectrum"]
+        ]
+    );
+    // One page of subcategories after the first and one page of pages
+    assert_eq!(server.get_hits("Category:Philosophy of mind"), 3);
+    assert_eq!(server.get_hits("Category:Color"), 0);
+    assert!(!crawler.is_complete());
+}
+
+#[tokio::test]
+async fn cycles_end_the_descent_and_are_reported() {
+    let wiki = MockWiki::new()
+        .categories("Category:B", &["Category:A"])
+        .categories("Category:C", &["Category:B"])
+        .categories("Category:A", &["Category:C"])
+        .categories("Category:D", &["Category:A", "Category:B"])
+        .categories("Page", &["Category:D"]);
+    let server = MockServer::start(wiki).await;
+
+    let mut crawler = crawler(&server, "Category:A");
+    crawler.compute_layers_async(10).await.unwrap();
+    let report = crawler.get_report();
+
+    assert!(crawler.is_complete());
+    assert_eq!(
+        layer_categories(&report),
+        vec![vec!["Category:A"], vec!["Category:B", "Category:D"], vec!["Category:C"]]
+    );
+    assert_eq!(layer_pages(&report), vec![vec![], vec!["Page"], vec![]]);
+    // Category:D is reached twice without being a cycle
+    assert_eq!(report.cycles.len(), 1);
+    assert_eq!(
+        report.cycles[0].categories,
+        vec!["Category:A", "Category:B", "Category:C", "Category:A"]
+    );
+    for category in ["Category:A", "Category:B", "Category:C", "Category:D"] {
+        assert_eq!(server.get_hits(category), 1);
+    }
+}
+
+#[tokio::test]
+async fn failed_categories_are_reported() {
+    let wiki = MockWiki::new()
+        .categories("Category:Broken", &["Category:Root"])
+        .categories("Member", &["Category:Broken"])
+        .categories("Page", &["Category:Root"])
+        .fault("Category:Broken", Fault::Status(500), usize::MAX);
+    let server = MockServer::start(wiki).await;
+
+    let mut crawler = crawler(&server, "Category:Root");
+    crawler.compute_layers_async(3).await.unwrap();
+    let report = crawler.get_report();
+
+    assert_eq!(layer_pages(&report), vec![vec!["Page"], vec![]]);
+    assert_eq!(report.failures.len(), 1);
+    assert_eq!(report.failures[0].article, "Category:Broken");
+    assert_eq!(crawler.get_pages(), vec![Title::from_text("Page", true)]);
+}
+
+#[test]
+fn root_must_be_a_category() {
+    assert!(CategoryCrawler::new(Title::from_text("Philosophy of mind", true)).is_err());
+}
@@ -0,0 +1,194 @@
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use bzip2::write::BzEncoder;
+use bzip2::Compression;
+
+use wiki_utils::dump::{DumpPage, DumpReader, DumpSource};
+use wiki_utils::source::ArticleSource;
+use wiki_utils::title::{Title, MAIN_NAMESPACE};
+use wiki_utils::wikitext::{extract_wikilinks, parse_redirect};
+
+// Trimmed pages-articles export with both kinds of redirect, a talk page and a page with two revisions
+const DUMP_XML: &str = r#"<mediawiki xmlns="http://www.mediawiki.org/xml/export-0.11/" version="0.11" xml:lang="en">
+  <siteinfo>
+    <sitename>Wikipedia</sitename>
+    <namespaces>
+      <namespace key="0" case="first-letter" />
+      <namespace key="1" case="first-letter">Talk</namespace>
+    </namespaces>
+  </siteinfo>
+  <page>
+    <title>Philosophy of mind</title>
+    <ns>0</ns>
+    <id>1</id>
+    <revision>
+      <id>100</id>
+      <text bytes="200" xml:space="preserve">'''Philosophy of mind''' studies the [[mind|mental]]
+and its relation to the [[Human_body#Brain|body]].
+See [[#History|below]], [[Category:Philosophy]] and [[AT&amp;T]]. [[File:Brain.png|thumb|The [[brain]]]]
+Also [[qualia]], [[Caf%C3%A9]] and [[ Intentionality ]].</text>
+    </revision>
+  </page>
+  <page>
+    <title>Mind</title>
+    <ns>0</ns>
+    <id>2</id>
+    <revision>
+      <id>200</id>
+      <text xml:space="preserve">[[Philosophy of mind]]</text>
+    </revision>
+    <revision>
+      <id>199</id>
+      <text xml:space="preserve">[[Qualia]]</text>
+    </revision>
+  </page>
+  <page>
+    <title>Mental</title>
+    <ns>0</ns>
+    <id>3</id>
+    <redirect title="Mind" />
+    <revision>
+      <id>300</id>
+      <text xml:space="preserve">#REDIRECT [[Mind]]</text>
+    </revision>
+  </page>
+  <page>
+    <title>Minds</title>
+    <ns>0</ns>
+    <id>4</id>
+    <revision>
+      <id>400</id>
+      <text xml:space="preserve">#redirect: [[mind#Definitions|Mind]]</text>
+    </revision>
+  </page>
+  <page>
+    <title>Talk:Mind</title>
+    <ns>1</ns>
+    <id>5</id>
+    <revision>
+      <id>500</id>
+      <text xml:space="preserve">[[Mind]] needs sources</text>
+    </revision>
+  </page>
+  <page>
+    <title>Qualia</title>
+    <ns>0</ns>
+    <id>6</id>
+    <revision>
+      <id>600</id>
+      <text xml:space="preserve">[[Philosophy_of_mind]]</text>
+    </revision>
+  </page>
+</mediawiki>
+"#;
+
+fn page(title: &str, text: &str) -> DumpPage {
+    DumpPage {
+        title: title.to_string(),
+        namespace: MAIN_NAMESPACE,
+        redirect: None,
+        text: text.to_string(),
+    }
+}
+
+fn write_dump(directory: &Path, compressed: bool) -> PathBuf {
+    fs::create_dir_all(directory).unwrap();
+    if compressed {
+        let path = directory.join("pages-articles.xml.bz2");
+        let mut encoder = BzEncoder::new(fs::File::create(&path).unwrap(), Compression::fast());
+        encoder.write_all(DUMP_XML.as_bytes()).unwrap();
+        encoder.finish().unwrap();
+        path
+    } else {
+        let path = directory.join("pages-articles.xml");
+        fs::write(&path, DUMP_XML).unwrap();
+        path
+    }
+}
+
+#[test]
+fn encoded_titles_resolve_to_dump_articles() {
+    let pages = [
+        page("Café", "[[100% Pure]]"),
+        page("100% Pure", "[[Café]]"),
+        page("Cafe", "#REDIRECT [[Café]]"),
+    ];
+    let dump = DumpSource::from_pages(pages.into_iter().map(Ok)).unwrap();
+    let cafe = Title::from_text("Café", true);
+    let pure = Title::from_text("100% Pure", true);
+
+    assert_eq!(dump.resolve(&Title::from_endpoint("Caf%C3%A9").unwrap()), Some(&cafe));
+    assert_eq!(dump.resolve(&Title::from_text("café", true)), Some(&cafe));
+    assert_eq!(dump.resolve(&Title::from_text("Cafe", true)), Some(&cafe));
+    // A '%' that is part of the title is neither decoded nor encoded a second time
+    assert_eq!(dump.resolve(&Title::from_text("100%_Pure", true)), Some(&pure));
+    assert_eq!(dump.resolve(&Title::from_endpoint("100%25_Pure").unwrap()), Some(&pure));
+    assert_eq!(dump.resolve(&Title::from_text("100%25 Pure", true)), None);
+}
+
+#[test]
+fn wikilinks_are_normalized_like_link_targets() {
+    let text = "[[mind|mental]], [[Human_body#Brain|body]], [[#History|below]], [[Category:Philosophy]], [[ Intentionality ]], \
+                [[File:Brain.png|thumb|The [[brain]]]], [[unclosed";
+
+    assert_eq!(extract_wikilinks(text), vec!["Mind", "Human body", "Intentionality", "Brain"]);
+}
+
+#[test]
+fn redirects_are_parsed_from_text() {
+    assert_eq!(parse_redirect("#REDIRECT [[Mind]]"), Some(String::from("Mind")));
+    assert_eq!(parse_redirect("  #redirect: [[mind#Definitions|Mind]]"), Some(String::from("Mind")));
+    assert_eq!(
+        parse_redirect("#Redirect [[philosophy_of mind]] {{R from move}}"),
+        Some(String::from("Philosophy of mind"))
+    );
+    assert_eq!(parse_redirect("#REDIRECT [[#Section]]"), None);
+    assert_eq!(parse_redirect("See [[Mind]]"), None);
+}
+
+#[tokio::test]
+async fn plain_and_compressed_dumps_are_read_alike() {
+    let directory = std::env::temp_dir().join(format!("wiki-utils-dump-{}", std::process::id()));
+    let plain = write_dump(&directory, false);
+    let compressed = write_dump(&directory, true);
+
+    let plain_pages: Vec<DumpPage> = DumpReader::open(&plain).unwrap().collect::<Result<_, _>>().unwrap();
+    let compressed_pages: Vec<DumpPage> = DumpReader::open(&compressed).unwrap().collect::<Result<_, _>>().unwrap();
+    let summary = |pages: &[DumpPage]| -> Vec<(String, i64, Option<String>)> {
+        pages
+            .iter()
+            .map(|page| (page.title.clone(), page.namespace, page.get_redirect_target()))
+            .collect()
+    };
+    assert_eq!(summary(&plain_pages), summary(&compressed_pages));
+    assert_eq!(
+        summary(&plain_pages),
+        vec![
+            (String::from("Philosophy of mind"), 0, None),
+            (String::from("Mind"), 0, None),
+            (String::from("Mental"), 0, Some(String::from("Mind"))),
+            (String::from("Minds"), 0, Some(String::from("Mind"))),
+            (String::from("Talk:Mind"), 1, None),
+            (String::from("Qualia"), 0, None),
+        ]
+    );
+    // Entities are resolved and only the first revision is kept
+    assert!(plain_pages[0].text.contains("[[AT&T]]"));
+    assert_eq!(plain_pages[1].text, "[[Philosophy of mind]]");
+
+    let dump = DumpSource::open(&compressed).unwrap();
+    fs::remove_dir_all(&directory).unwrap();
+    assert_eq!(dump.get_article_count(), 3);
+
+    let start = Title::from_text("Philosophy of mind", true);
+    let links = dump.get_article_links(&start).await.unwrap().links;
+    // Links to pages missing from the dump are dropped. '[[Caf%C3%A9]]' is a title with a '%' in it, not an endpoint
+    assert_eq!(links, vec![Title::from_text("Mind", true), Title::from_text("Qualia", true)]);
+
+    let redirected = dump.get_article_links(&Title::from_text("minds", true)).await.unwrap();
+    assert_eq!(redirected.endpoint, Title::from_text("Mind", true));
+    assert_eq!(redirected.links, vec![start.clone()]);
+    assert!(dump.get_article_links(&Title::from_text("Talk:Mind", true)).await.is_err());
+}
@@ -0,0 +1,173 @@
+// Runs LinkCalculator against responses recorded with '--record' from a small local wiki:
+//
+// Alpha -> Beta, Gamma, Help:Contents, Beta#History
+// Beta  -> Alpha, Delta, Epsilon
+// Gamma -> Delta, Missing_page
+// Delta -> Gamma, Zeta
+// Zeta  -> Alpha, Eta
+// Eta   -> Theta
+//
+// Epsilon redirects to Zeta and Missing_page does not exist
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use tokio::sync::mpsc;
+
+use wiki_utils::client::{AsyncClient, ClientError, LinkBackend};
+use wiki_utils::fixture::FixtureStore;
+use wiki_utils::links::{LinkCalcError, LinkCalculator};
+use wiki_utils::report::LinkReport;
+use wiki_utils::site::Site;
+use wiki_utils::title::Title;
+
+const FIXTURE_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/small_wiki");
+
+fn title(text: &str) -> Title {
+    Title::from_text(text, true)
+}
+
+fn titles(texts: &[&str]) -> Vec<Title> {
+    texts.iter().map(|text| title(text)).collect()
+}
+
+fn replay_client(backend: LinkBackend) -> AsyncClient {
+    AsyncClient::builder()
+        .with_site(Site::new("http", "localhost:8770", "/wiki/", "/w/api.php"))
+        .with_link_backend(backend)
+        .with_fixtures(FixtureStore::replayer(FIXTURE_DIR))
+        .build()
+        .unwrap()
+}
+
+async fn crawl(backend: LinkBackend, layers: usize) -> LinkCalculator {
+    let mut calc = LinkCalculator::new(title("Alpha"));
+    calc.set_source(Arc::new(replay_client(backend)));
+    calc.compute_layers_async(layers).await.unwrap();
+    calc
+}
+
+fn layer_articles(report: &LinkReport) -> Vec<Vec<&str>> {
+    report
+        .layers
+        .iter()
+        .map(|layer| layer.articles.iter().map(String::as_str).collect())
+        .collect()
+}
+
+#[tokio::test]
+async fn html_backend_finds_every_layer() {
+    let report = crawl(LinkBackend::Html, 4).await.get_report().unwrap();
+
+    assert_eq!(report.start, "Alpha");
+    assert_eq!(
+        layer_articles(&report),
+        vec![
+            vec!["Alpha"],
+            vec!["Beta", "Gamma"],
+            vec!["Delta", "Missing page", "Zeta"],
+            vec!["Eta"],
+            vec!["Theta"]
+        ]
+    );
+}
+
+#[tokio::test]
+async fn api_backend_matches_html_backend() {
+    let html_report = crawl(LinkBackend::Html, 4).await.get_report().unwrap();
+    let api_report = crawl(LinkBackend::Api, 4).await.get_report().unwrap();
+
+    assert_eq!(layer_articles(&api_report), layer_articles(&html_report));
+}
+
+#[tokio::test]
+async fn redirects_are_resolved() {
+    let calc = crawl(LinkBackend::Html, 3).await;
+    let report = calc.get_report().unwrap();
+
+    assert_eq!(report.redirects.len(), 1);
+    assert_eq!(report.redirects[0].from, "Epsilon");
+    assert_eq!(report.redirects[0].to, "Zeta");
+    assert_eq!(calc.get_children(&title("Beta")), titles(&["Alpha", "Delta", "Zeta"]));
+    assert_eq!(calc.get_parents(&title("Epsilon")), titles(&["Beta", "Delta"]));
+}
+
+#[tokio::test]
+async fn missing_articles_are_reported_as_failures() {
+    for backend in [LinkBackend::Html, LinkBackend::Api] {
+        let report = crawl(backend, 3).await.get_report().unwrap();
+
+        assert_eq!(report.failures.len(), 1, "{:?}", backend);
+        assert_eq!(report.failures[0].article, "Missing page");
+    }
+}
+
+#[tokio::test]
+async fn trace_follows_shortest_chain() {
+    let calc = crawl(LinkBackend::Html, 4).await;
+
+    let trace = calc.trace_to_start(&title("Theta")).unwrap().unwrap();
+    assert_eq!(trace, titles(&["Alpha", "Beta", "Zeta", "Eta", "Theta"]));
+    assert_eq!(calc.trace_to_start(&title("Omega")).unwrap(), None);
+}
+
+#[tokio::test]
+async fn resuming_from_checkpoint_matches_uninterrupted_crawl() {
+    let full_report = crawl(LinkBackend::Html, 4).await.get_report().unwrap();
+
+    let checkpoint = crawl(LinkBackend::Html, 2).await.to_checkpoint().unwrap();
+    let mut calc = LinkCalculator::from_checkpoint(checkpoint).unwrap();
+    calc.set_source(Arc::new(replay_client(LinkBackend::Html)));
+    calc.compute_layers_async(2).await.unwrap();
+
+    assert_eq!(layer_articles(&calc.get_report().unwrap()), layer_articles(&full_report));
+}
+
+#[tokio::test]
+async fn parents_are_restored_from_checkpoint() {
+    let calc = crawl(LinkBackend::Html, 4).await;
+    let mut restored = LinkCalculator::from_checkpoint(calc.to_checkpoint().unwrap()).unwrap();
+
+    assert_eq!(restored.get_parents(&title("Zeta")), titles(&["Beta", "Delta"]));
+    assert_eq!(
+        restored.trace_to_start(&title("Theta")).unwrap(),
+        calc.trace_to_start(&title("Theta")).unwrap()
+    );
+
+    let (sender, mut receiver) = mpsc::unbounded_channel();
+    restored.set_discovery_sender(sender).unwrap();
+    let mut parents = HashMap::new();
+    while let Ok(discovered) = receiver.try_recv() {
+        parents.insert(discovered.title, discovered.parent);
+    }
+    assert_eq!(parents["Alpha"], None);
+    // Delta links to Zeta too, but is in the same layer
+    assert_eq!(parents["Zeta"].as_deref(), Some("Beta"));
+    assert_eq!(parents["Theta"].as_deref(), Some("Eta"));
+}
+
+#[tokio::test]
+async fn unrecorded_requests_fail() {
+    let client = replay_client(LinkBackend::Html);
+
+    let result = client.get_article("Omega").await;
+    assert!(matches!(result, Err(ClientError::MissingFixture(_))));
+}
+
+#[tokio::test]
+async fn recorded_error_status_is_replayed() {
+    let client = replay_client(LinkBackend::Html);
+
+    let error = client.get_article("Missing_page").await.err().unwrap();
+    assert_eq!(error.status_code().map(|code| code.as_u16()), Some(404));
+}
+
+#[test]
+fn errors_describe_their_cause() {
+    let missing_parent = LinkCalcError::MissingParentError(String::from("Theta"));
+    assert!(missing_parent.to_string().contains("'Theta'"));
+
+    let client_error = LinkCalcError::from(ClientError::NotCached(String::from("Omega")));
+    assert!(client_error
+        .to_string()
+        .contains(&ClientError::NotCached(String::from("Omega")).to_string()));
+}
@@ -0,0 +1,446 @@
+mod support;
+
+use std::fs;
+use std::sync::Arc;
+use std::time::Duration;
+
+use wiki_utils::cache::{ArticleCache, CacheMode};
+use wiki_utils::client::{AsyncClient, LinkBackend};
+use wiki_utils::fixture::FixtureStore;
+use wiki_utils::links::LinkCalculator;
+use wiki_utils::report::LinkReport;
+use wiki_utils::retry::RetryPolicy;
+use wiki_utils::title::Title;
+use wiki_utils::url::normalize_title;
+
+use support::{Fault, MockServer, MockWiki};
+
+fn fast_retries() -> RetryPolicy {
+    RetryPolicy::new()
+        .with_max_attempts(3)
+        .with_backoff(Duration::from_millis(10), Duration::from_millis(50))
+}
+
+fn client(server: &MockServer, backend: LinkBackend) -> AsyncClient {
+    AsyncClient::builder()
+        .with_site(server.site())
+        .with_link_backend(backend)
+        .with_retry_policy(fast_retries())
+        .build()
+        .unwrap()
+}
+
+async fn crawl(client: AsyncClient, start: &str, layers: usize) -> LinkReport {
+    let mut calc = LinkCalculator::new(Title::from_text(start, true));
+    calc.set_source(Arc::new(client));
+    calc.compute_layers_async(layers).await.unwrap();
+    calc.get_report().unwrap()
+}
+
+fn layer_articles(report: &LinkReport) -> Vec<Vec<&str>> {
+    report
+        .layers
+        .iter()
+        .map(|layer| layer.articles.iter().map(String::as_str).collect())
+        .collect()
+}
+
+fn failed_articles(report: &LinkReport) -> Vec<&str> {
+    report.failures.iter().map(|failure| failure.article.as_str()).collect()
+}
+
+#[tokio::test]
+async fn cycles_are_only_visited_once() {
+    let wiki = MockWiki::new().article("A", &["B"]).article("B", &["C"]).article("C", &["A", "B"]);
+    let server = MockServer::start(wiki).await;
+
+    let report = crawl(client(&server, LinkBackend::Html), "A", 5).await;
+
+    assert_eq!(
+        layer_articles(&report),
+        vec![vec!["A"], vec!["B"], vec!["C"], vec![], vec![], vec![]]
+    );
+    assert_eq!(server.get_hits("A"), 1);
+    assert_eq!(server.get_hits("B"), 1);
+    assert_eq!(server.get_hits("C"), 1);
+}
+
+#[tokio::test]
+async fn redirects_are_followed_and_reported() {
+    let wiki = MockWiki::new()
+        .article("Start", &["Old name", "Other"])
+        .redirect("Old name", "New name")
+        .article("New name", &["Leaf"])
+        .article("Other", &["New name"])
+        .article("Leaf", &[]);
+    let server = MockServer::start(wiki).await;
+
+    for backend in [LinkBackend::Html, LinkBackend::Api] {
+        let report = crawl(client(&server, backend), "Start", 3).await;
+
+        assert_eq!(
+            layer_articles(&report),
+            vec![vec!["Start"], vec!["New name", "Other"], vec!["Leaf"], vec![]],
+            "{:?}",
+            backend
+        );
+        assert_eq!(report.redirects.len(), 1);
+        assert_eq!(report.redirects[0].from, "Old name");
+        assert_eq!(report.redirects[0].to, "New name");
+        assert!(report.failures.is_empty());
+    }
+}
+
+#[tokio::test]
+async fn missing_articles_fail_without_retries() {
+    let wiki = MockWiki::new().article("Start", &["Red link", "Real"]).article("Real", &[]);
+    let server = MockServer::start(wiki).await;
+
+    let report = crawl(client(&server, LinkBackend::Html), "Start", 2).await;
+
+    assert_eq!(failed_articles(&report), vec!["Red link"]);
+    assert!(report.failures[0].reason.contains("404"));
+    assert_eq!(server.get_hits("Red link"), 1);
+}
+
+#[tokio::test]
+async fn rate_limited_requests_are_retried() {
+    let wiki = MockWiki::new()
+        .article("Start", &["Busy"])
+        .article("Busy", &["End"])
+        .fault("Busy", Fault::RateLimited(0), 2);
+    let server = MockServer::start(wiki).await;
+
+    let report = crawl(client(&server, LinkBackend::Html), "Start", 2).await;
+
+    assert_eq!(layer_articles(&report), vec![vec!["Start"], vec!["Busy"], vec!["End"]]);
+    assert!(report.failures.is_empty());
+    assert_eq!(server.get_hits("Busy"), 3);
+}
+
+#[tokio::test]
+async fn persistent_server_errors_become_failures() {
+    let wiki = MockWiki::new()
+        .article("Start", &["Broken", "Fine"])
+        .article("Broken", &["Hidden"])
+        .article("Fine", &[])
+        .fault("Broken", Fault::Status(503), usize::MAX);
+    let server = MockServer::start(wiki).await;
+
+    let report = crawl(client(&server, LinkBackend::Html), "Start", 2).await;
+
+    assert_eq!(failed_articles(&report), vec!["Broken"]);
+    assert_eq!(layer_articles(&report)[2], Vec::<&str>::new());
+    assert_eq!(server.get_hits("Broken"), fast_retries().get_max_attempts());
+}
+
+#[tokio::test]
+async fn slow_responses_time_out() {
+    let wiki = MockWiki::new()
+        .article("Start", &["Slow"])
+        .article("Slow", &[])
+        .fault("Slow", Fault::Delay(Duration::from_secs(5)), 1);
+    let server = MockServer::start(wiki).await;
+    let client = AsyncClient::builder()
+        .with_site(server.site())
+        .with_retry_policy(RetryPolicy::none())
+        .with_read_timeout(Duration::from_millis(100))
+        .build()
+        .unwrap();
+
+    let report = crawl(client, "Start", 2).await;
+
+    assert_eq!(failed_articles(&report), vec!["Slow"]);
+}
+
+#[tokio::test]
+async fn api_backend_batches_requests() {
+    let links: Vec<String> = (0..120).map(|i| format!("Page {}", i)).collect();
+    let link_refs: Vec<&str> = links.iter().map(String::as_str).collect();
+    let mut wiki = MockWiki::new().article("Hub", &link_refs);
+    for link in &links {
+        wiki = wiki.article(link, &["Hub"]);
+    }
+    let server = MockServer::start(wiki).await;
+
+    let report = crawl(client(&server, LinkBackend::Api), "Hub", 2).await;
+
+    assert_eq!(report.layers[1].articles.len(), 120);
+    assert!(report.layers[2].articles.is_empty());
+    // One query for the hub and one per 50 titles of the first layer
+    assert_eq!(server.get_api_request_count(), 1 + 3);
+    assert_eq!(server.get_hits("Hub"), 0);
+}
+
+#[tokio::test]
+async fn recorded_crawl_replays_without_server() {
+    let directory = std::env::temp_dir().join(format!("wiki-utils-fixtures-{}", std::process::id()));
+    let wiki = MockWiki::new()
+        .article("A", &["B", "Gone"])
+        .article("B", &["C", "Moved"])
+        .redirect("Moved", "D")
+        .article("C", &[])
+        .article("D", &["A"]);
+    let server = MockServer::start(wiki).await;
+    let site = server.site();
+
+    let recorder = AsyncClient::builder()
+        .with_site(site.clone())
+        .with_fixtures(FixtureStore::recorder(&directory))
+        .build()
+        .unwrap();
+    let recorded = crawl(recorder, "A", 3).await;
+    drop(server);
+
+    let replayer = AsyncClient::builder()
+        .with_site(site)
+        .with_fixtures(FixtureStore::replayer(&directory))
+        .build()
+        .unwrap();
+    let replayed = crawl(replayer, "A", 3).await;
+    fs::remove_dir_all(&directory).unwrap();
+
+    assert_eq!(layer_articles(&replayed), layer_articles(&recorded));
+    assert_eq!(failed_articles(&replayed), vec!["Gone"]);
+    assert_eq!(replayed.redirects.len(), 1);
+}
+
+#[tokio::test]
+async fn titles_with_reserved_characters_are_requested() {
+    let wiki = MockWiki::new()
+        .article("What?", &[])
+        .article("AT&T", &[])
+        .article("Rock & roll", &[]);
+    let server = MockServer::start(wiki).await;
+    let client = client(&server, LinkBackend::Html);
+
+    for title in ["What?", "AT&T", "rock_&  roll"] {
+        let article = client.get_article(title).await.unwrap();
+        assert_eq!(article.get_endpoint().to_string(), normalize_title(title, true));
+    }
+    assert_eq!(server.get_hits("What?"), 1);
+}
+
+#[tokio::test]
+async fn article_names_are_not_decoded() {
+    let wiki = MockWiki::new().article("Café", &[]);
+    let server = MockServer::start(wiki).await;
+    let client = client(&server, LinkBackend::Html);
+
+    // A name that happens to decode is requested as typed instead of as the title it decodes to
+    assert!(client.get_article("Caf%C3%A9").await.is_err());
+    assert_eq!(client.get_article("Café").await.unwrap().get_endpoint().to_string(), "Café");
+    assert_eq!(server.get_hits("Café"), 1);
+}
+
+#[tokio::test]
+async fn first_letter_is_only_capitalized_if_the_site_does() {
+    let wiki = MockWiki::new().article("apple", &[]);
+    let server = MockServer::start(wiki).await;
+
+    let capitalizing = client(&server, LinkBackend::Html);
+    assert!(capitalizing.get_article("apple").await.is_err());
+
+    let case_sensitive = AsyncClient::with_site(server.site().with_capital_links(false));
+    assert_eq!(
+        case_sensitive.get_article("apple").await.unwrap().get_endpoint().get_endpoint(),
+        "apple"
+    );
+}
+
+#[tokio::test]
+async fn category_edges_reach_category_neighborhoods() {
+    let wiki = MockWiki::new()
+        .article("Start", &["Other"])
+        .article("Other", &[])
+        .article("Thinker", &[])
+        .categories("Start", &["Category:Minds"])
+        .categories("Thinker", &["Category:Minds"])
+        .categories("Category:Minds", &["Category:Philosophy"]);
+    let server = MockServer::start(wiki).await;
+
+    let report = crawl(client(&server, LinkBackend::Html), "Start", 2).await;
+    assert_eq!(layer_articles(&report), vec![vec!["Start"], vec!["Other"], vec![]]);
+
+    for backend in [LinkBackend::Html, LinkBackend::Api] {
+        let mut calc = LinkCalculator::new(Title::from_text("Start", true));
+        calc.set_source(Arc::new(client(&server, backend)));
+        calc.set_category_edges(true);
+        calc.compute_layers_async(3).await.unwrap();
+        let report = calc.get_report().unwrap();
+
+        assert_eq!(
+            layer_articles(&report),
+            vec![
+                vec!["Start"],
+                vec!["Category:Minds", "Other"],
+                vec!["Category:Philosophy", "Thinker"],
+                vec![]
+            ],
+            "{:?}",
+            backend
+        );
+        assert!(report.failures.is_empty());
+    }
+}
+
+#[tokio::test]
+async fn category_edges_follow_every_page_of_a_listing() {
+    let mut wiki = MockWiki::new()
+        .listing_size(2)
+        .article("Start", &[])
+        .categories("Start", &["Category:Minds"]);
+    for member in ["Member A", "Member B", "Member C", "Member D"] {
+        wiki = wiki.article(member, &[]).categories(member, &["Category:Minds"]);
+    }
+    for subcategory in ["Category:Sub A", "Category:Sub B", "Category:Sub C"] {
+        wiki = wiki.categories(subcategory, &["Category:Minds"]);
+    }
+    let server = MockServer::start(wiki).await;
+
+    for backend in [LinkBackend::Html, LinkBackend::Api] {
+        let mut calc = LinkCalculator::new(Title::from_text("Start", true));
+        calc.set_source(Arc::new(client(&server, backend)));
+        calc.set_category_edges(true);
+        calc.compute_layers_async(1).await.unwrap();
+
+        // Category edges are kept when resuming without setting them again
+        let checkpoint = calc.to_checkpoint().unwrap();
+        assert!(checkpoint.category_edges);
+        let mut calc = LinkCalculator::from_checkpoint(checkpoint).unwrap();
+        calc.set_source(Arc::new(client(&server, backend)));
+        calc.compute_layers_async(1).await.unwrap();
+        let report = calc.get_report().unwrap();
+
+        assert_eq!(
+            layer_articles(&report),
+            vec![
+                vec!["Start"],
+                vec!["Category:Minds"],
+                vec![
+                    "Category:Sub A",
+                    "Category:Sub B",
+                    "Category:Sub C",
+                    "Member A",
+                    "Member B",
+                    "Member C",
+                    "Member D"
+                ],
+            ],
+            "{:?}",
+            backend
+        );
+        assert!(report.failures.is_empty());
+    }
+}
+
+#[tokio::test]
+async fn links_are_read_from_the_sites_article_path() {
+    let wiki = MockWiki::new()
+        .article_path("/index.php/")
+        .article("Start", &["Old name", "Other#History"])
+        .redirect("Old name", "New name")
+        .article("New name", &[])
+        .article("Other", &["Thinker"])
+        .article("Thinker", &[])
+        .categories("Start", &["Category:Minds"])
+        .categories("Thinker", &["Category:Minds"]);
+    let server = MockServer::start(wiki).await;
+    assert_eq!(server.site().get_article_path(), "/index.php/");
+
+    let mut calc = LinkCalculator::new(Title::from_text("Start", true));
+    calc.set_source(Arc::new(client(&server, LinkBackend::Html)));
+    calc.set_category_edges(true);
+    calc.compute_layers_async(2).await.unwrap();
+    let report = calc.get_report().unwrap();
+
+    assert_eq!(
+        layer_articles(&report),
+        vec![vec!["Start"], vec!["Category:Minds", "New name", "Other"], vec!["Thinker"]]
+    );
+    assert_eq!(report.redirects.len(), 1);
+    assert!(report.failures.is_empty());
+}
+
+#[tokio::test]
+async fn articles_are_cached_under_their_endpoint() {
+    let directory = std::env::temp_dir().join(format!("wiki-utils-article-cache-{}", std::process::id()));
+    let wiki = MockWiki::new().article("Philosophy of mind", &[]);
+    let server = MockServer::start(wiki).await;
+    let cached_client = |mode: CacheMode| {
+        let mut cache = ArticleCache::new(&directory, Duration::from_secs(3600));
+        cache.set_mode(mode);
+        AsyncClient::builder().with_site(server.site()).with_cache(cache).build().unwrap()
+    };
+
+    let client = cached_client(CacheMode::ReadWrite);
+    for name in ["philosophy of mind", "Philosophy_of_mind", "Philosophy  of mind"] {
+        client.get_article(name).await.unwrap();
+    }
+    let offline = cached_client(CacheMode::Offline).get_article("Philosophy of_mind").await;
+    fs::remove_dir_all(&directory).unwrap();
+
+    assert_eq!(server.get_hits("Philosophy of mind"), 1);
+    assert_eq!(offline.unwrap().get_endpoint().to_string(), "Philosophy of mind");
+}
+
+#[tokio::test]
+async fn stale_link_lists_are_revalidated_by_revision() {
+    let directory = std::env::temp_dir().join(format!("wiki-utils-cache-{}", std::process::id()));
+    let wiki = MockWiki::new().article("A", &["B", "C"]).article("B", &[]).article("C", &["A"]);
+    let server = MockServer::start(wiki).await;
+    let cached_client = |mode: CacheMode| {
+        let mut cache = ArticleCache::new(&directory, Duration::ZERO);
+        cache.set_mode(mode);
+        AsyncClient::builder()
+            .with_site(server.site())
+            .with_link_backend(LinkBackend::Api)
+            .with_cache(cache)
+            .with_retry_policy(fast_retries())
+            .build()
+            .unwrap()
+    };
+
+    let fetched = crawl(cached_client(CacheMode::ReadWrite), "A", 2).await;
+    let links_queries = server.get_api_prop_count("links|categories|info");
+    // Ages are counted in whole seconds. The revisions the mock reports never change
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    let revalidated = crawl(cached_client(CacheMode::ReadWrite), "A", 2).await;
+    let offline = crawl(cached_client(CacheMode::Offline), "A", 2).await;
+    fs::remove_dir_all(&directory).unwrap();
+
+    assert_eq!(layer_articles(&revalidated), layer_articles(&fetched));
+    assert_eq!(layer_articles(&offline), layer_articles(&fetched));
+    assert_eq!(server.get_api_prop_count("links|categories|info"), links_queries);
+    assert_eq!(server.get_api_prop_count("info"), 2);
+}
+
+#[tokio::test]
+async fn redirect_targets_are_only_in_one_layer() {
+    let mut wiki = MockWiki::new()
+        .article("Start", &["Alias A", "Alias B", "Other A", "Other B", "Other C"])
+        .redirect("Alias A", "Target")
+        .redirect("Alias B", "Target")
+        .article("Target", &["Leaf"])
+        .article("Leaf", &["Target"]);
+    // Whichever of these is stored first, 'Target' is only known to be in layer 1 once the layer is normalized
+    for other in ["Other A", "Other B", "Other C"] {
+        wiki = wiki.article(other, &["Target", "Alias B", "Start"]);
+    }
+    let server = MockServer::start(wiki).await;
+
+    for backend in [LinkBackend::Html, LinkBackend::Api] {
+        let report = crawl(client(&server, backend), "Start", 3).await;
+        let layers = layer_articles(&report);
+
+        assert_eq!(
+            layers,
+            vec![vec!["Start"], vec!["Other A", "Other B", "Other C", "Target"], vec!["Leaf"], vec![]],
+            "{:?}",
+            backend
+        );
+        let total: usize = layers.iter().map(Vec::len).sum();
+        let distinct: std::collections::HashSet<&str> = layers.into_iter().flatten().collect();
+        assert_eq!(distinct.len(), total, "{:?}", backend);
+    }
+}
@@ -0,0 +1,76 @@
+mod support;
+
+use std::sync::Arc;
+
+use wiki_utils::client::AsyncClient;
+use wiki_utils::path::PathFinder;
+
+use support::{MockServer, MockWiki};
+
+fn path_finder(server: &MockServer) -> PathFinder {
+    let mut path_finder = PathFinder::new();
+    path_finder.set_source(Arc::new(AsyncClient::with_site(server.site())));
+    path_finder
+}
+
+#[tokio::test]
+async fn links_to_redirects_are_resolved_before_they_are_visited() {
+    let wiki = MockWiki::new()
+        .article("Start", &["Bridge link"])
+        .redirect("Bridge link", "Bridge")
+        .article("Bridge", &["Goal"])
+        .article("Goal", &[]);
+    let server = MockServer::start(wiki).await;
+
+    let path = path_finder(&server).find_path("Start", "Goal").await.unwrap();
+
+    assert_eq!(
+        path,
+        Some(vec![String::from("Start"), String::from("Bridge"), String::from("Goal")])
+    );
+    assert_eq!(server.get_hits("Bridge link"), 0);
+    assert_eq!(server.get_hits("Bridge"), 1);
+}
+
+#[tokio::test]
+async fn every_page_of_what_links_here_is_read() {
+    let wiki = MockWiki::new()
+        .listing_size(2)
+        .article("Start", &["First", "Second", "Zeta"])
+        .article("Linker one", &["Goal"])
+        .article("Linker two", &["Goal"])
+        .article("Zeta", &["Goal"])
+        .article("Goal", &[]);
+    let server = MockServer::start(wiki).await;
+
+    let path = path_finder(&server).find_path("Start", "Goal").await.unwrap();
+
+    assert_eq!(path, Some(vec![String::from("Start"), String::from("Zeta"), String::from("Goal")]));
+    assert_eq!(server.get_hits("Special:WhatLinksHere/Goal"), 2);
+    // The sides met on the second page without expanding the forward side again
+    assert_eq!(server.get_hits("Zeta"), 0);
+}
+
+#[tokio::test]
+async fn articles_with_a_colon_are_found_backward() {
+    let wiki = MockWiki::new()
+        .article("Start", &["Star Wars: Episode IV", "Other"])
+        .article("Other", &[])
+        .article("Star Wars: Episode IV", &["Goal"])
+        .article("Talk:Goal", &["Goal"])
+        .article("Goal", &[]);
+    let server = MockServer::start(wiki).await;
+
+    let path = path_finder(&server).find_path("Start", "Goal").await.unwrap();
+
+    assert_eq!(
+        path,
+        Some(vec![
+            String::from("Start"),
+            String::from("Star Wars: Episode IV"),
+            String::from("Goal")
+        ])
+    );
+    // Links from other namespaces are not followed
+    assert_eq!(server.get_hits("Talk:Goal"), 0);
+}
@@ -0,0 +1,21 @@
+use std::time::Duration;
+
+use tokio::time;
+
+use wiki_utils::ratelimit::{RateLimiter, MIN_REQUESTS_PER_SECOND};
+
+#[tokio::test]
+async fn tiny_rates_wait_instead_of_overflowing() {
+    for rate in [f64::MIN_POSITIVE, 1e-300, 0.0, -1.0, f64::NAN] {
+        let limiter = RateLimiter::new(rate, 1);
+        assert_eq!(limiter.get_requests_per_second(), MIN_REQUESTS_PER_SECOND);
+
+        // The burst is available at once and the next token is a day away
+        limiter.acquire().await;
+        assert!(
+            time::timeout(Duration::from_millis(10), limiter.acquire()).await.is_err(),
+            "{}",
+            rate
+        );
+    }
+}
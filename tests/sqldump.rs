@@ -0,0 +1,135 @@
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+
+use wiki_utils::source::ArticleSource;
+use wiki_utils::sqldump::{LinkGraph, SqlDumpFiles};
+use wiki_utils::title::Title;
+
+// Trimmed mysqldump output. Only the INSERT statements are read
+const PAGE_SQL: &str = "-- MySQL dump 10.19\n\
+    CREATE TABLE `page` (\n  `page_id` int(8) unsigned NOT NULL AUTO_INCREMENT\n);\n\
+    INSERT INTO `page` VALUES (1,0,'Philosophy_of_mind',0,0,0.5,'20240101000000',NULL,100,2000,'wikitext',NULL),\
+    (2,0,'Mind',0,0,0.5,'20240101000000',NULL,200,1000,'wikitext',NULL);\n\
+    INSERT INTO `page` VALUES (3,0,'Mental',1,0,0.5,'20240101000000',NULL,300,20,'wikitext',NULL),\
+    (4,1,'Mind',0,0,0.5,'20240101000000',NULL,400,50,'wikitext',NULL),\
+    (5,0,'O\\'Brien\\\\Ryan',0,0,0.5,'20240101000000',NULL,500,10,'wikitext',NULL);\n";
+const LINKTARGET_SQL: &str = "INSERT INTO `linktarget` VALUES (10,0,'Mind'),(11,0,'Mental'),(12,0,'Missing'),\
+    (13,0,'O\\'Brien\\\\Ryan'),(14,1,'Mind'),(15,0,'Philosophy_of_mind');\n";
+const PAGELINKS_SQL: &str = "INSERT INTO `pagelinks` VALUES (1,0,10),(1,0,11),(1,0,12),(1,0,13),(1,0,14),(2,0,15),(4,1,10);\n";
+const REDIRECT_SQL: &str = "INSERT INTO `redirect` VALUES (3,0,'Mind','',NULL),(5,0,'Mind','en','');\n";
+
+fn write_dumps(directory: &Path) -> SqlDumpFiles {
+    fs::create_dir_all(directory).unwrap();
+    fs::write(directory.join("testwiki-latest-page.sql"), PAGE_SQL).unwrap();
+    fs::write(directory.join("testwiki-latest-linktarget.sql"), LINKTARGET_SQL).unwrap();
+    fs::write(directory.join("testwiki-latest-redirect.sql"), REDIRECT_SQL).unwrap();
+    let mut encoder = GzEncoder::new(
+        fs::File::create(directory.join("testwiki-latest-pagelinks.sql.gz")).unwrap(),
+        Compression::fast(),
+    );
+    encoder.write_all(PAGELINKS_SQL.as_bytes()).unwrap();
+    encoder.finish().unwrap();
+    SqlDumpFiles::find(directory).unwrap()
+}
+
+fn temp_directory(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("wiki-utils-{}-{}", name, std::process::id()))
+}
+
+async fn sorted_links(graph: &LinkGraph, title: &str) -> Vec<String> {
+    let mut links: Vec<String> = graph
+        .get_article_links(&Title::from_text(title, true))
+        .await
+        .unwrap()
+        .links
+        .iter()
+        .map(Title::to_string)
+        .collect();
+    links.sort();
+    links
+}
+
+#[tokio::test]
+async fn insert_statements_are_parsed_into_a_graph() {
+    let directory = temp_directory("sql-import");
+    let graph = LinkGraph::import(&write_dumps(&directory)).unwrap();
+    fs::remove_dir_all(&directory).unwrap();
+
+    // Talk pages, links to them and links to missing articles are dropped
+    assert_eq!(graph.get_article_count(), 4);
+    assert_eq!(graph.get_link_count(), 4);
+    assert_eq!(
+        sorted_links(&graph, "Philosophy of mind").await,
+        vec!["Mental", "Mind", "O'Brien\\Ryan"]
+    );
+    assert_eq!(
+        graph.resolve(&Title::from_text("Mental", true)),
+        Some(Title::from_text("Mind", true))
+    );
+    // Interwiki redirects stay articles of their own
+    assert_eq!(
+        graph.resolve(&Title::from_text("O'Brien\\Ryan", true)),
+        Some(Title::from_text("O'Brien\\Ryan", true))
+    );
+    assert_eq!(graph.resolve(&Title::from_text("Talk:Mind", true)), None);
+    assert_eq!(graph.resolve(&Title::from_text("Missing", true)), None);
+}
+
+#[tokio::test]
+async fn saved_graphs_load_unchanged() {
+    let directory = temp_directory("sql-round-trip");
+    let graph = LinkGraph::import(&write_dumps(&directory)).unwrap();
+    let graph_file = directory.join("graph.bin");
+    graph.save(&graph_file).unwrap();
+    let loaded = LinkGraph::load(&graph_file).unwrap();
+    fs::remove_dir_all(&directory).unwrap();
+
+    assert_eq!(loaded.get_article_count(), graph.get_article_count());
+    assert_eq!(loaded.get_link_count(), graph.get_link_count());
+    for title in ["Philosophy of mind", "Mind", "Mental", "O'Brien\\Ryan"] {
+        assert_eq!(sorted_links(&loaded, title).await, sorted_links(&graph, title).await, "{}", title);
+        assert_eq!(
+            loaded.resolve(&Title::from_text(title, true)),
+            graph.resolve(&Title::from_text(title, true))
+        );
+    }
+}
+
+#[test]
+fn corrupt_graph_files_are_rejected() {
+    let directory = temp_directory("sql-corrupt");
+    let graph = LinkGraph::import(&write_dumps(&directory)).unwrap();
+    let graph_file = directory.join("graph.bin");
+    graph.save(&graph_file).unwrap();
+    let bytes = fs::read(&graph_file).unwrap();
+    // Magic and node count, then the length and bytes of every title
+    let offsets_start = 16
+        + ["Philosophy_of_mind", "Mind", "Mental", "O'Brien\\Ryan"]
+            .iter()
+            .map(|title| 4 + title.len())
+            .sum::<usize>();
+
+    let load_modified = |modify: &dyn Fn(&mut Vec<u8>)| {
+        let mut modified = bytes.clone();
+        modify(&mut modified);
+        fs::write(&graph_file, &modified).unwrap();
+        LinkGraph::load(&graph_file)
+    };
+    assert!(load_modified(&|bytes| bytes.truncate(bytes.len() / 2)).is_err());
+    assert!(load_modified(&|bytes| bytes[8..16].copy_from_slice(&u64::MAX.to_le_bytes())).is_err());
+    assert!(load_modified(&|bytes| bytes[16..20].copy_from_slice(&u32::MAX.to_le_bytes())).is_err());
+    // An offset past the next one would slice the targets backwards
+    assert!(load_modified(&|bytes| bytes[offsets_start + 8..offsets_start + 16].copy_from_slice(&1000u64.to_le_bytes())).is_err());
+    // A redirect from a node that does not exist
+    assert!(load_modified(&|bytes| {
+        let from = bytes.len() - 8;
+        bytes[from..from + 4].copy_from_slice(&u32::MAX.to_le_bytes())
+    })
+    .is_err());
+    assert!(load_modified(&|_| ()).is_ok());
+    fs::remove_dir_all(&directory).unwrap();
+}
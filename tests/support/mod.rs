@@ -0,0 +1,570 @@
+// Each test crate only uses part of this module
+#![allow(dead_code)]
+
+use std::collections::HashMap;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use reqwest::Url;
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+use tokio::time;
+
+use wiki_utils::site::Site;
+use wiki_utils::url::{decode_url_str, encode_url_str};
+
+const DEFAULT_ARTICLE_PATH: &str = "/wiki/";
+const API_PATH: &str = "/w/api.php";
+const INDEX_PATH: &str = "/w/index.php";
+const WHAT_LINKS_HERE_PREFIX: &str = "Special:WhatLinksHere/";
+// Entries on each page of 'Special:WhatLinksHere' when neither the request nor listing_size limits them
+const DEFAULT_WHAT_LINKS_HERE_LIMIT: usize = 50;
+
+#[derive(Debug, Clone)]
+enum MockPage {
+    Article(Vec<String>),
+    Redirect(String),
+}
+
+// Injected into the responses for a title
+#[derive(Debug, Clone, Copy)]
+pub enum Fault {
+    // Responds with the status code instead of the page
+    Status(u16),
+    // Responds with 429 and a Retry-After of the given seconds
+    RateLimited(u64),
+    // Waits before responding with the page
+    Delay(Duration),
+}
+
+// Declarative description of a wiki. Titles that are neither articles nor redirects respond with 404, except for
+// categories that have members
+//
+// MockWiki::new()
+//     .article("Alpha", &["Beta", "Gamma"])
+//     .redirect("Gamma", "Beta")
+//     .categories("Alpha", &["Category:Letters"])
+//     .fault("Beta", Fault::Status(503), 2)
+//     .listing_size(200)
+//     .article_path("/index.php/")
+#[derive(Debug, Clone, Default)]
+pub struct MockWiki {
+    pages: HashMap<String, MockPage>,
+    // Endpoint -> fault and how many more requests it applies to
+    faults: HashMap<String, (Fault, usize)>,
+    // Endpoint -> titles of the categories the page is in, in the order they were added
+    categories: Vec<(String, Vec<String>)>,
+    // Most subcategories and most pages on each page of a category listing, and most entries on each page of
+    // 'Special:WhatLinksHere'. Unlimited if None
+    listing_size: Option<usize>,
+    // Prefix of article urls and of the hrefs linking to them. '/wiki/' if None
+    article_path: Option<String>,
+}
+
+impl MockWiki {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn article(mut self, title: &str, links: &[&str]) -> Self {
+        let links = links.iter().map(|link| link.to_string()).collect();
+        self.pages.insert(encode_url_str(title), MockPage::Article(links));
+        self
+    }
+
+    // Responds with a 301 to the target's page. The Action API reports it in 'redirects'
+    pub fn redirect(mut self, title: &str, target: &str) -> Self {
+        self.pages.insert(encode_url_str(title), MockPage::Redirect(target.to_string()));
+        self
+    }
+
+    // Category pages list their members whether or not they are added as articles
+    pub fn categories(mut self, title: &str, categories: &[&str]) -> Self {
+        let categories = categories.iter().map(|category| category.to_string()).collect();
+        self.categories.push((encode_url_str(title), categories));
+        self
+    }
+
+    fn get_categories(&self, endpoint: &str) -> Vec<String> {
+        self.categories
+            .iter()
+            .filter(|(page, _)| page == endpoint)
+            .flat_map(|(_, categories)| categories.clone())
+            .collect()
+    }
+
+    // Titles of the pages in the category, subcategories included
+    fn get_members(&self, category_endpoint: &str) -> Vec<String> {
+        self.categories
+            .iter()
+            .filter(|(_, categories)| categories.iter().any(|member_of| encode_url_str(member_of) == category_endpoint))
+            .map(|(page, _)| decode_url_str(page).unwrap_or_default().replace('_', " "))
+            .collect()
+    }
+
+    // Titles of the articles that link to the page, ignoring fragments
+    fn get_linking_titles(&self, endpoint: &str) -> Vec<String> {
+        let mut titles: Vec<String> = self
+            .pages
+            .iter()
+            .filter(|(_, page)| match page {
+                MockPage::Article(links) => links
+                    .iter()
+                    .any(|link| encode_url_str(link.split('#').next().unwrap_or_default()) == endpoint),
+                MockPage::Redirect(_) => false,
+            })
+            .map(|(page, _)| decode_url_str(page).unwrap_or_default().replace('_', " "))
+            .collect();
+        titles.sort();
+        titles
+    }
+
+    fn get_redirects_to(&self, endpoint: &str) -> Vec<String> {
+        let mut titles: Vec<String> = self
+            .pages
+            .iter()
+            .filter(|(_, page)| matches!(page, MockPage::Redirect(target) if encode_url_str(target) == endpoint))
+            .map(|(page, _)| decode_url_str(page).unwrap_or_default().replace('_', " "))
+            .collect();
+        titles.sort();
+        titles
+    }
+
+    // Applies to the first 'times' requests for the title. usize::MAX applies to every request
+    pub fn fault(mut self, title: &str, fault: Fault, times: usize) -> Self {
+        self.faults.insert(encode_url_str(title), (fault, times));
+        self
+    }
+
+    // Longer listings link to their next page through '/w/index.php?title=...&pagefrom=...' like MediaWiki does
+    pub fn listing_size(mut self, listing_size: usize) -> Self {
+        self.listing_size = Some(listing_size);
+        self
+    }
+
+    // Serves articles under the path instead of '/wiki/'. The path should start and end with '/'
+    pub fn article_path(mut self, article_path: &str) -> Self {
+        self.article_path = Some(article_path.to_string());
+        self
+    }
+}
+
+struct MockResponse {
+    status: u16,
+    headers: Vec<(&'static str, String)>,
+    content_type: &'static str,
+    body: String,
+}
+
+impl MockResponse {
+    fn html(status: u16, body: String) -> Self {
+        MockResponse {
+            status,
+            headers: Vec::new(),
+            content_type: "text/html; charset=UTF-8",
+            body,
+        }
+    }
+
+    fn json(value: Value) -> Self {
+        MockResponse {
+            status: 200,
+            headers: Vec::new(),
+            content_type: "application/json; charset=utf-8",
+            body: value.to_string(),
+        }
+    }
+
+    fn with_header(mut self, name: &'static str, value: String) -> Self {
+        self.headers.push((name, value));
+        self
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut head = format!("HTTP/1.1 {} Mock\r\n", self.status);
+        head.push_str(&format!(
+            "Content-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+            self.content_type,
+            self.body.len()
+        ));
+        for (name, value) in &self.headers {
+            head.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        head.push_str("\r\n");
+        let mut bytes = head.into_bytes();
+        bytes.extend_from_slice(self.body.as_bytes());
+        bytes
+    }
+}
+
+struct ServerState {
+    wiki: Mutex<MockWiki>,
+    article_path: String,
+    // Endpoint -> number of article requests received for it
+    hits: Mutex<HashMap<String, usize>>,
+    api_requests: Mutex<usize>,
+    // 'prop' parameter -> number of api requests made with it
+    api_props: Mutex<HashMap<String, usize>>,
+}
+
+impl ServerState {
+    async fn respond(&self, target: &str) -> MockResponse {
+        let url = match Url::parse(&format!("http://mock{}", target)) {
+            Ok(url) => url,
+            Err(_) => return MockResponse::html(400, String::new()),
+        };
+        let params: HashMap<String, String> = url.query_pairs().into_owned().collect();
+        let endpoint = match url.path().strip_prefix(self.article_path.as_str()) {
+            Some(endpoint) => Some(decode_url_str(endpoint).map(|title| encode_url_str(&title)).unwrap_or_default()),
+            None if url.path() == INDEX_PATH => Some(params.get("title").map(|title| encode_url_str(title)).unwrap_or_default()),
+            None => None,
+        };
+        if let Some(endpoint) = endpoint {
+            return match endpoint.strip_prefix(WHAT_LINKS_HERE_PREFIX) {
+                Some(target) => self.respond_what_links_here(target, &params),
+                None => self.respond_article(&endpoint, &params).await,
+            };
+        }
+        if url.path() == API_PATH {
+            return self.respond_api(&params);
+        }
+        MockResponse::html(404, String::new())
+    }
+
+    // Listing pages of a category count as requests for the category's page
+    async fn respond_article(&self, endpoint: &str, params: &HashMap<String, String>) -> MockResponse {
+        *self.hits.lock().unwrap().entry(endpoint.to_string()).or_default() += 1;
+
+        let fault = {
+            let mut wiki = self.wiki.lock().unwrap();
+            match wiki.faults.get_mut(endpoint) {
+                Some((fault, remaining)) if *remaining > 0 => {
+                    *remaining = remaining.saturating_sub(1);
+                    Some(*fault)
+                }
+                _ => None,
+            }
+        };
+        match fault {
+            Some(Fault::Status(status)) => return MockResponse::html(status, String::new()),
+            Some(Fault::RateLimited(seconds)) => {
+                return MockResponse::html(429, String::new()).with_header("Retry-After", seconds.to_string())
+            }
+            Some(Fault::Delay(delay)) => time::sleep(delay).await,
+            None => (),
+        }
+
+        let wiki = self.wiki.lock().unwrap().clone();
+        let (categories, members) = (wiki.get_categories(endpoint), wiki.get_members(endpoint));
+        let listing = Listing {
+            members: &members,
+            size: wiki.listing_size,
+            subcategories_from: params.get("subcatfrom").map(|from| from.replace('_', " ")),
+            pages_from: params.get("pagefrom").map(|from| from.replace('_', " ")),
+        };
+        let article_path = self.article_path.as_str();
+        match wiki.pages.get(endpoint).cloned() {
+            Some(MockPage::Article(links)) => {
+                MockResponse::html(200, render_article(article_path, endpoint, &links, &categories, &listing))
+            }
+            None if !members.is_empty() => MockResponse::html(200, render_article(article_path, endpoint, &[], &categories, &listing)),
+            Some(MockPage::Redirect(target)) => {
+                MockResponse::html(301, String::new()).with_header("Location", format!("{}{}", self.article_path, encode_url_str(&target)))
+            }
+            None => MockResponse::html(
+                404,
+                "<html><body>There is currently no text in this page.</body></html>".to_string(),
+            ),
+        }
+    }
+
+    // Lists the articles that link to the target, then each redirect to it with the articles that link to the redirect.
+    // 'offset' is the index of the first entry on the page
+    fn respond_what_links_here(&self, target: &str, params: &HashMap<String, String>) -> MockResponse {
+        *self
+            .hits
+            .lock()
+            .unwrap()
+            .entry(format!("{}{}", WHAT_LINKS_HERE_PREFIX, target))
+            .or_default() += 1;
+        let wiki = self.wiki.lock().unwrap();
+
+        let mut entries: Vec<String> = wiki
+            .get_linking_titles(target)
+            .iter()
+            .map(|title| format!("<li>{}</li>", render_link(&self.article_path, title)))
+            .collect();
+        for redirect in wiki.get_redirects_to(target) {
+            let linking_titles = wiki.get_linking_titles(&encode_url_str(&redirect));
+            entries.push(format!(
+                "<li><a href=\"{}?title={}&amp;redirect=no\">{}</a> (redirect page){}</li>",
+                INDEX_PATH,
+                encode_url_str(&redirect),
+                redirect,
+                render_list(&self.article_path, &linking_titles.iter().collect::<Vec<_>>())
+            ));
+        }
+
+        let limit = params
+            .get("limit")
+            .and_then(|limit| limit.parse().ok())
+            .unwrap_or(DEFAULT_WHAT_LINKS_HERE_LIMIT);
+        let size = wiki.listing_size.map_or(limit, |size| size.min(limit));
+        let start = params
+            .get("offset")
+            .and_then(|offset| offset.parse().ok())
+            .unwrap_or(0)
+            .min(entries.len());
+        let end = (start + size).min(entries.len());
+        let next = if end < entries.len() {
+            format!(
+                "(<a href=\"{}?title={}{}&amp;namespace=0&amp;limit={}&amp;offset={}\" class=\"mw-nextlink\">next {}</a>)",
+                INDEX_PATH, WHAT_LINKS_HERE_PREFIX, target, limit, end, limit
+            )
+        } else {
+            String::new()
+        };
+        MockResponse::html(
+            200,
+            format!(
+                "<!DOCTYPE html><html><body><div id=\"mw-content-text\"><ul id=\"mw-whatlinkshere-list\">{}</ul>{}</div></body></html>",
+                entries[start..end].concat(),
+                next
+            ),
+        )
+    }
+
+    // Supports the 'action=query&prop=links|categories' and 'list=categorymembers' requests of the api backend. Faults
+    // only apply to article pages
+    fn respond_api(&self, params: &HashMap<String, String>) -> MockResponse {
+        *self.api_requests.lock().unwrap() += 1;
+        if let Some(prop) = params.get("prop") {
+            *self.api_props.lock().unwrap().entry(prop.clone()).or_default() += 1;
+        }
+        let wiki = self.wiki.lock().unwrap();
+
+        if let Some(category) = params.get("cmtitle") {
+            let members: Vec<Value> = wiki
+                .get_members(&encode_url_str(category))
+                .into_iter()
+                .map(|title| json!({ "ns": if title.starts_with("Category:") { 14 } else { 0 }, "title": title }))
+                .collect();
+            return MockResponse::json(json!({ "batchcomplete": true, "query": { "categorymembers": members } }));
+        }
+
+        let mut normalized = Vec::new();
+        let mut redirects = Vec::new();
+        let mut pages = Vec::new();
+        for title in params
+            .get("titles")
+            .map_or("", String::as_str)
+            .split('|')
+            .filter(|title| !title.is_empty())
+        {
+            let mut page_title = title.replace('_', " ");
+            if page_title != title {
+                normalized.push(json!({ "from": title, "to": page_title }));
+            }
+            if let Some(MockPage::Redirect(target)) = wiki.pages.get(&encode_url_str(title)) {
+                let target = target.replace('_', " ");
+                redirects.push(json!({ "from": page_title, "to": target }));
+                page_title = target;
+            }
+            let page_endpoint = encode_url_str(&page_title);
+            let categories: Vec<Value> = wiki
+                .get_categories(&page_endpoint)
+                .iter()
+                .map(|category| json!({ "ns": 14, "title": category.replace('_', " ") }))
+                .collect();
+            match wiki.pages.get(&page_endpoint) {
+                Some(MockPage::Article(links)) => {
+                    let links: Vec<Value> = links
+                        .iter()
+                        .filter(|link| !link.contains(':'))
+                        .map(|link| json!({ "ns": 0, "title": link.split('#').next().unwrap_or_default().replace('_', " ") }))
+                        .collect();
+                    pages.push(json!({ "title": page_title, "lastrevid": 1, "links": links, "categories": categories }));
+                }
+                None if !wiki.get_members(&page_endpoint).is_empty() => {
+                    pages.push(json!({ "title": page_title, "lastrevid": 1, "links": [], "categories": categories }));
+                }
+                _ => pages.push(json!({ "title": page_title, "missing": true })),
+            }
+        }
+        let query = json!({ "normalized": normalized, "redirects": redirects, "pages": pages });
+        MockResponse::json(json!({ "batchcomplete": true, "query": query }))
+    }
+}
+
+// The part of a category's members shown on one page of its listing
+struct Listing<'a> {
+    members: &'a [String],
+    size: Option<usize>,
+    // First subcategory and first page to show. The start of the listing if None
+    subcategories_from: Option<String>,
+    pages_from: Option<String>,
+}
+
+// Just enough of MediaWiki's markup for Article to find the heading, lead, links, categories and category members
+fn render_article(article_path: &str, endpoint: &str, links: &[String], categories: &[String], listing: &Listing) -> String {
+    let title = decode_url_str(endpoint).unwrap_or_default().replace('_', " ");
+    let anchors: Vec<String> = links
+        .iter()
+        .map(|link| {
+            let href = match link.split_once('#') {
+                Some((target, fragment)) => format!("{}#{}", encode_url_str(target), fragment),
+                None => encode_url_str(link),
+            };
+            format!("<a href=\"{}{}\">{}</a>", article_path, href, link)
+        })
+        .collect();
+    let (mut subcategories, mut pages): (Vec<&String>, Vec<&String>) =
+        listing.members.iter().partition(|member| member.starts_with("Category:"));
+    subcategories.sort();
+    pages.sort();
+    let subcategories_from = listing.subcategories_from.as_deref();
+    format!(
+        "<!DOCTYPE html><html><head><title>{title}</title></head><body>\
+         <h1 id=\"firstHeading\"><span class=\"mw-page-title-main\">{title}</span></h1>\
+         <div id=\"mw-content-text\"><div class=\"mw-parser-output\"><p>{title} links to {links}</p></div>\
+         <div class=\"mw-category-generated\"><div id=\"mw-subcategories\">{subcategories}</div>\
+         <div id=\"mw-pages\">{pages}</div></div></div>\
+         <div id=\"catlinks\" class=\"catlinks\"><div id=\"mw-normal-catlinks\" class=\"mw-normal-catlinks\">{categories}</div></div>\
+         </body></html>",
+        title = title,
+        links = anchors.join(", "),
+        subcategories = render_listing(
+            article_path,
+            endpoint,
+            &subcategories,
+            subcategories_from,
+            listing.size,
+            "subcatfrom"
+        ),
+        pages = render_listing(
+            article_path,
+            endpoint,
+            &pages,
+            listing.pages_from.as_deref(),
+            listing.size,
+            "pagefrom"
+        ),
+        categories = render_list(article_path, &categories.iter().collect::<Vec<_>>())
+    )
+}
+
+// Links to the next page of the listing above the list if there are more titles than fit
+fn render_listing(
+    article_path: &str,
+    endpoint: &str,
+    titles: &[&String],
+    from: Option<&str>,
+    size: Option<usize>,
+    from_param: &str,
+) -> String {
+    let start = from.map_or(0, |from| {
+        titles.iter().position(|title| title.as_str() >= from).unwrap_or(titles.len())
+    });
+    let end = size.map_or(titles.len(), |size| (start + size).min(titles.len()));
+    let next = match titles.get(end) {
+        Some(next) => format!(
+            "(<a href=\"{}?title={}&amp;{}={}\">next page</a>)",
+            INDEX_PATH,
+            endpoint,
+            from_param,
+            encode_url_str(next)
+        ),
+        None => String::new(),
+    };
+    next + &render_list(article_path, &titles[start..end])
+}
+
+fn render_list(article_path: &str, titles: &[&String]) -> String {
+    let items: String = titles
+        .iter()
+        .map(|title| format!("<li>{}</li>", render_link(article_path, title)))
+        .collect();
+    format!("<ul>{}</ul>", items)
+}
+
+fn render_link(article_path: &str, title: &str) -> String {
+    format!("<a href=\"{}{}\">{}</a>", article_path, encode_url_str(title), title)
+}
+
+// Serves a MockWiki on a random local port until dropped
+pub struct MockServer {
+    address: SocketAddr,
+    state: Arc<ServerState>,
+    handle: JoinHandle<()>,
+}
+
+impl MockServer {
+    pub async fn start(wiki: MockWiki) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.expect("Can bind to a local port");
+        let address = listener.local_addr().expect("Listener has an address");
+        let state = Arc::new(ServerState {
+            article_path: wiki.article_path.clone().unwrap_or_else(|| DEFAULT_ARTICLE_PATH.to_string()),
+            wiki: Mutex::new(wiki),
+            hits: Mutex::new(HashMap::new()),
+            api_requests: Mutex::new(0),
+            api_props: Mutex::new(HashMap::new()),
+        });
+
+        let accept_state = state.clone();
+        let handle = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(handle_connection(stream, accept_state.clone()));
+            }
+        });
+
+        MockServer { address, state, handle }
+    }
+
+    pub fn site(&self) -> Site {
+        Site::new("http", &self.address.to_string(), &self.state.article_path, API_PATH)
+    }
+
+    // Requests for the article's page, including ones that were answered with a fault
+    pub fn get_hits(&self, title: &str) -> usize {
+        self.state.hits.lock().unwrap().get(&encode_url_str(title)).copied().unwrap_or(0)
+    }
+
+    pub fn get_api_request_count(&self) -> usize {
+        *self.state.api_requests.lock().unwrap()
+    }
+
+    // Api requests with exactly this 'prop' parameter. e.g. 'info' for revision checks
+    pub fn get_api_prop_count(&self, prop: &str) -> usize {
+        self.state.api_props.lock().unwrap().get(prop).copied().unwrap_or(0)
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+// One request per connection
+async fn handle_connection(stream: TcpStream, state: Arc<ServerState>) -> io::Result<()> {
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).await?;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header).await? == 0 || header == "\r\n" {
+            break;
+        }
+    }
+
+    let target = request_line.split_whitespace().nth(1).unwrap_or("/");
+    let response = state.respond(target).await;
+
+    let mut stream = reader.into_inner();
+    stream.write_all(&response.to_bytes()).await?;
+    stream.shutdown().await
+}
@@ -0,0 +1,68 @@
+use std::collections::HashSet;
+
+use wiki_utils::title::{Title, MAIN_NAMESPACE};
+
+#[test]
+fn spellings_of_the_same_page_are_equal() {
+    let spellings = [
+        "Direct and indirect realism",
+        "direct_and_indirect_realism",
+        "  Direct   and_indirect realism ",
+        ":Direct and indirect realism",
+    ];
+    let titles: HashSet<Title> = spellings.iter().map(|text| Title::from_text(text, true)).collect();
+
+    assert_eq!(titles.len(), 1);
+    assert_eq!(
+        Title::from_endpoint("Direct_and_indirect_realism").unwrap(),
+        Title::from_text(spellings[1], true)
+    );
+    assert_eq!(
+        Title::from_endpoint("Direct%20and%20indirect%20realism").unwrap(),
+        Title::from_text(spellings[0], true)
+    );
+}
+
+#[test]
+fn namespace_prefixes_are_parsed() {
+    let help = Title::from_text("help:editing pages", true);
+    assert_eq!(help.get_namespace(), 12);
+    assert_eq!(help.get_db_key(), "Editing_pages");
+    assert_eq!(help.to_string(), "Help:Editing pages");
+
+    assert_eq!(Title::from_text("Image:Logo.png", true), Title::from_text("File:Logo.png", true));
+    assert_eq!(Title::from_text("User talk:Example", true).get_namespace(), 3);
+}
+
+#[test]
+fn unknown_prefixes_stay_in_the_main_namespace() {
+    let title = Title::from_text("Star Wars: A New Hope", true);
+
+    assert_eq!(title.get_namespace(), MAIN_NAMESPACE);
+    assert_eq!(title.get_db_key(), "Star_Wars:_A_New_Hope");
+}
+
+#[test]
+fn endpoints_round_trip() {
+    for text in ["AT&T", "Rock & roll", "What?", "Talk:Café", "C#"] {
+        let title = Title::from_text(text, true);
+        assert_eq!(Title::from_endpoint(&title.get_endpoint()).unwrap(), title);
+    }
+    assert_eq!(Title::from_text("Talk:Café", true).get_endpoint(), "Talk:Caf%C3%A9");
+}
+
+#[test]
+fn capitalization_follows_the_site() {
+    assert_eq!(Title::from_text("iPod", true).get_db_key(), "IPod");
+    assert_eq!(Title::from_text("iPod", false).get_db_key(), "iPod");
+    assert_ne!(Title::from_endpoint("apple").unwrap(), Title::from_endpoint("Apple").unwrap());
+}
+
+#[test]
+fn serialized_as_endpoint() {
+    let title = Title::from_text("Help:Editing pages", true);
+    let json = serde_json::to_string(&title).unwrap();
+
+    assert_eq!(json, "\"Help:Editing_pages\"");
+    assert_eq!(serde_json::from_str::<Title>(&json).unwrap(), title);
+}
@@ -0,0 +1,37 @@
+use wiki_utils::site::{Project, Site};
+use wiki_utils::url::{decode_url_str, encode_title, normalize_title};
+
+#[test]
+fn titles_are_normalized() {
+    assert_eq!(
+        normalize_title("  direct_and   indirect realism ", true),
+        "Direct and indirect realism"
+    );
+    assert_eq!(normalize_title("éclair", true), "Éclair");
+    assert_eq!(normalize_title("apple_pie", false), "apple pie");
+    assert_eq!(normalize_title(" _ ", true), "");
+}
+
+#[test]
+fn reserved_characters_are_encoded() {
+    assert_eq!(encode_title("What?", true), "What%3F");
+    assert_eq!(encode_title("AT&T", true), "AT%26T");
+    assert_eq!(encode_title("C#", true), "C%23");
+    assert_eq!(encode_title("100% pure", true), "100%25_pure");
+    assert_eq!(encode_title("Rust (programming language)", true), "Rust_(programming_language)");
+    assert_eq!(encode_title("Talk:Café", true), "Talk:Caf%C3%A9");
+}
+
+#[test]
+fn encoded_titles_decode_to_normalized_form() {
+    for title in ["what?", "AT&T", "Café au lait", "1+1=2"] {
+        assert_eq!(decode_url_str(&encode_title(title, true)).unwrap(), normalize_title(title, true));
+    }
+}
+
+#[test]
+fn wiktionary_is_case_sensitive() {
+    assert!(Site::wikipedia("en").get_capital_links());
+    assert!(!Site::project("en", Project::Wiktionary).get_capital_links());
+    assert!(!"fr.wiktionary".parse::<Site>().unwrap().get_capital_links());
+}